@@ -1,8 +1,9 @@
 #![no_std]
 
-use glam::{Vec2, Vec4, Vec4Swizzles};
+use glam::{Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles, vec2};
 use spirv_std::image::sample_with::lod;
 use spirv_std::image::{Image2d, ImageWithMethods};
+use spirv_std::num_traits::Float;
 use spirv_std::{Sampler, spirv};
 
 pub struct FrameData {
@@ -67,3 +68,108 @@ pub fn fs_masked(
         spirv_std::arch::kill();
     }
 }
+
+pub struct PostParams {
+    /// bloom threshold, bloom intensity, vignette strength, color grading strength
+    settings: Vec4,
+    /// size of a texel of the input in xy, blur step in texels in zw
+    texel: Vec4,
+}
+
+/// edge length of the color grading cube, stored as slices side by side
+const LUT_SIZE: f32 = 16.;
+
+/// A triangle covering the screen, draw with 3 vertices and no vertex buffer
+#[spirv(vertex)]
+pub fn vs_fullscreen(
+    #[spirv(vertex_index)] vertex_index: i32,
+    vtx_uv: &mut Vec2,
+    #[spirv(position)] gl_position: &mut Vec4,
+) {
+    let uv = vec2(((vertex_index << 1) & 2) as f32, (vertex_index & 2) as f32);
+    let position = uv * 2. - 1.;
+    *vtx_uv = vec2(uv.x, 1. - uv.y);
+    // flipped just like `vs_main`
+    *gl_position = Vec4::new(position.x, -position.y, 0., 1.);
+}
+
+/// Keeps what is brighter than the threshold, averaging 4 texels to downsample by half
+#[spirv(fragment)]
+pub fn fs_bloom_extract(
+    vtx_uv: Vec2,
+    #[spirv(descriptor_set = 0, binding = 0, uniform)] params: &PostParams,
+    #[spirv(descriptor_set = 1, binding = 0)] input: &Image2d,
+    #[spirv(descriptor_set = 1, binding = 1)] input_sampler: &Sampler,
+    color_out: &mut Vec4,
+) {
+    let texel = params.texel.xy() * 0.5;
+    let color = (input.sample(*input_sampler, vtx_uv + vec2(-texel.x, -texel.y))
+        + input.sample(*input_sampler, vtx_uv + vec2(texel.x, -texel.y))
+        + input.sample(*input_sampler, vtx_uv + vec2(-texel.x, texel.y))
+        + input.sample(*input_sampler, vtx_uv + vec2(texel.x, texel.y)))
+        .xyz()
+        * 0.25;
+    let bright = (color - Vec3::splat(params.settings.x)).max(Vec3::ZERO);
+    *color_out = bright.extend(1.);
+}
+
+/// One direction of a separable 9 tap gaussian blur
+#[spirv(fragment)]
+pub fn fs_blur(
+    vtx_uv: Vec2,
+    #[spirv(descriptor_set = 0, binding = 0, uniform)] params: &PostParams,
+    #[spirv(descriptor_set = 1, binding = 0)] input: &Image2d,
+    #[spirv(descriptor_set = 1, binding = 1)] input_sampler: &Sampler,
+    color_out: &mut Vec4,
+) {
+    let step = params.texel.xy() * params.texel.zw();
+    let tap = |offset: f32| {
+        (input.sample(*input_sampler, vtx_uv + step * offset)
+            + input.sample(*input_sampler, vtx_uv - step * offset))
+        .xyz()
+    };
+    let color = input.sample(*input_sampler, vtx_uv).xyz() * 0.227027
+        + tap(1.) * 0.1945946
+        + tap(2.) * 0.1216216
+        + tap(3.) * 0.054054
+        + tap(4.) * 0.016216;
+    *color_out = color.extend(1.);
+}
+
+/// Adds the bloom, then darkens the edges and grades the colors through the lookup table
+#[spirv(fragment)]
+pub fn fs_composite(
+    vtx_uv: Vec2,
+    #[spirv(descriptor_set = 0, binding = 0, uniform)] params: &PostParams,
+    #[spirv(descriptor_set = 1, binding = 0)] scene: &Image2d,
+    #[spirv(descriptor_set = 1, binding = 1)] scene_sampler: &Sampler,
+    #[spirv(descriptor_set = 2, binding = 0)] bloom: &Image2d,
+    #[spirv(descriptor_set = 2, binding = 1)] bloom_sampler: &Sampler,
+    #[spirv(descriptor_set = 3, binding = 0)] lut: &Image2d,
+    #[spirv(descriptor_set = 3, binding = 1)] lut_sampler: &Sampler,
+    color_out: &mut Vec4,
+) {
+    let settings = params.settings;
+    let mut color = scene.sample(*scene_sampler, vtx_uv).xyz()
+        + bloom.sample(*bloom_sampler, vtx_uv).xyz() * settings.y;
+    // clamp without shifting the hue of over bright colors
+    color /= color.max_element().max(1.);
+
+    let from_center = (vtx_uv - 0.5) * 2.;
+    color *= (1. - settings.z * from_center.length_squared() * 0.5).max(0.);
+
+    // sampled even without grading, to stay in uniform control flow
+    let blue = color.z * (LUT_SIZE - 1.);
+    let slice = blue.floor();
+    let next = (slice + 1.).min(LUT_SIZE - 1.);
+    let within = (color.xy() * (LUT_SIZE - 1.) + 0.5) / vec2(LUT_SIZE * LUT_SIZE, LUT_SIZE);
+    let graded = lut
+        .sample(*lut_sampler, within + vec2(slice / LUT_SIZE, 0.))
+        .xyz()
+        .lerp(
+            lut.sample(*lut_sampler, within + vec2(next / LUT_SIZE, 0.)).xyz(),
+            blue - slice,
+        );
+    color = color.lerp(graded, settings.w);
+    *color_out = color.extend(1.);
+}
@@ -0,0 +1,297 @@
+use crate::entity::game::GameEvent;
+use crate::level::Level;
+use std::collections::{BTreeSet, VecDeque};
+
+/// how long a toast stays on screen, in seconds
+pub const TOAST_DURATION: f32 = 3.;
+
+/// What has to happen for an [`Achievement`] to unlock
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Condition {
+    /// finish a level, optionally a specific one and within limits of the attempt
+    FinishLevel {
+        /// [`LevelMeta::name`](crate::level::LevelMeta::name) of the level
+        level: Option<&'static str>,
+        max_bubbles: Option<u32>,
+        max_deaths: Option<u32>,
+    },
+    /// reveal at least this fraction of any level
+    Coverage { min: f32 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Achievement {
+    /// persisted, must never change
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub condition: Condition,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "finish_level",
+        name: "FIRST STEPS",
+        description: "FINISH A LEVEL",
+        condition: Condition::FinishLevel {
+            level: None,
+            max_bubbles: None,
+            max_deaths: None,
+        },
+    },
+    Achievement {
+        id: "no_bubbles",
+        name: "DRY RUN",
+        description: "FINISH A LEVEL WITHOUT BUBBLES",
+        condition: Condition::FinishLevel {
+            level: None,
+            max_bubbles: Some(0),
+            max_deaths: None,
+        },
+    },
+    Achievement {
+        id: "no_deaths_lvl14",
+        name: "UNTOUCHABLE",
+        description: "NO DEATHS ON LEVEL 14",
+        condition: Condition::FinishLevel {
+            level: Some("Lvl14"),
+            max_bubbles: None,
+            max_deaths: Some(0),
+        },
+    },
+    Achievement {
+        id: "paint_90",
+        name: "MASTERPIECE",
+        description: "PAINT 90% OF A LEVEL",
+        condition: Condition::Coverage { min: 0.9 },
+    },
+];
+
+/// What happened during the current level attempt, as far as conditions care
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attempt {
+    /// [`LevelMeta::name`](crate::level::LevelMeta::name) of the level
+    pub level: String,
+    pub bubbles: u32,
+    pub deaths: u32,
+}
+
+impl Condition {
+    /// whether the condition is met by `event`, with `attempt` already including it
+    pub fn is_met(&self, attempt: &Attempt, event: &GameEvent) -> bool {
+        match (*self, *event) {
+            (
+                Condition::FinishLevel {
+                    level,
+                    max_bubbles,
+                    max_deaths,
+                },
+                GameEvent::LevelComplete,
+            ) => {
+                level.is_none_or(|name| name == attempt.level)
+                    && max_bubbles.is_none_or(|max| attempt.bubbles <= max)
+                    && max_deaths.is_none_or(|max| attempt.deaths <= max)
+            }
+            (Condition::Coverage { min }, GameEvent::Painted { coverage, .. }) => coverage >= min,
+            _ => false,
+        }
+    }
+}
+
+/// Evaluates [`ACHIEVEMENTS`] against the events of the current attempt
+#[derive(Debug, Clone, Default)]
+pub struct AchievementTracker {
+    pub attempt: Attempt,
+}
+
+impl AchievementTracker {
+    pub fn new(level: &Level) -> Self {
+        let mut tracker = Self::default();
+        tracker.start_attempt(level);
+        tracker
+    }
+
+    pub fn start_attempt(&mut self, level: &Level) {
+        self.attempt = Attempt {
+            level: level.meta.name.clone(),
+            ..Attempt::default()
+        };
+    }
+
+    /// Feeds an event, adding newly met achievements to `unlocked` and returning them
+    pub fn observe(
+        &mut self,
+        event: &GameEvent,
+        unlocked: &mut BTreeSet<String>,
+    ) -> Vec<&'static Achievement> {
+        match event {
+            GameEvent::BubbleSpawned { .. } => self.attempt.bubbles += 1,
+            GameEvent::Death { .. } => self.attempt.deaths += 1,
+            _ => {}
+        }
+        let mut new = Vec::new();
+        for achievement in ACHIEVEMENTS {
+            if !unlocked.contains(achievement.id)
+                && achievement.condition.is_met(&self.attempt, event)
+            {
+                unlocked.insert(achievement.id.to_string());
+                new.push(achievement);
+            }
+        }
+        new
+    }
+}
+
+/// Announces unlocked achievements one after another
+#[derive(Debug, Clone, Default)]
+pub struct Toasts {
+    pub queue: VecDeque<&'static Achievement>,
+    /// seconds the front of the queue has been shown for
+    pub shown: f32,
+}
+
+impl Toasts {
+    pub fn push(&mut self, achievement: &'static Achievement) {
+        self.queue.push_back(achievement);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.queue.is_empty() {
+            return;
+        }
+        self.shown += dt;
+        if self.shown >= TOAST_DURATION {
+            self.queue.pop_front();
+            self.shown = 0.;
+        }
+    }
+
+    pub fn current(&self) -> Option<&'static Achievement> {
+        self.queue.front().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    fn feed(
+        level: &str,
+        events: &[GameEvent],
+        unlocked: &mut BTreeSet<String>,
+    ) -> Vec<&'static str> {
+        let mut tracker = AchievementTracker::default();
+        tracker.attempt.level = level.to_string();
+        events
+            .iter()
+            .flat_map(|event| tracker.observe(event, unlocked))
+            .map(|achievement| achievement.id)
+            .collect()
+    }
+
+    #[test]
+    fn finish_without_bubbles() {
+        let mut unlocked = BTreeSet::new();
+        let ids = feed(
+            "Lvl01",
+            &[GameEvent::Jump, GameEvent::LevelComplete],
+            &mut unlocked,
+        );
+        assert_eq!(ids, ["finish_level", "no_bubbles"]);
+    }
+
+    #[test]
+    fn bubbles_prevent_dry_run() {
+        let mut unlocked = BTreeSet::new();
+        let events = [
+            GameEvent::BubbleSpawned { pos: Vec2::ZERO },
+            GameEvent::LevelComplete,
+        ];
+        assert_eq!(feed("Lvl01", &events, &mut unlocked), ["finish_level"]);
+    }
+
+    #[test]
+    fn no_deaths_only_counts_on_its_level() {
+        let events = [
+            GameEvent::BubbleSpawned { pos: Vec2::ZERO },
+            GameEvent::LevelComplete,
+        ];
+        let mut unlocked = BTreeSet::from(["finish_level".to_string()]);
+        assert!(feed("Lvl13", &events, &mut unlocked).is_empty());
+        assert_eq!(feed("Lvl14", &events, &mut unlocked), ["no_deaths_lvl14"]);
+    }
+
+    #[test]
+    fn level_conditions_name_shipped_levels() {
+        let levels = Level::load_file_tree().unwrap();
+        for achievement in ACHIEVEMENTS {
+            if let Condition::FinishLevel {
+                level: Some(name), ..
+            } = achievement.condition
+            {
+                assert!(
+                    levels.iter().any(|level| level.meta.name == name),
+                    "{} names unknown level {name}",
+                    achievement.id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn deaths_prevent_untouchable() {
+        let mut unlocked = BTreeSet::from(["finish_level".to_string()]);
+        let events = [
+            GameEvent::BubbleSpawned { pos: Vec2::ZERO },
+            GameEvent::Death { pos: Vec2::ZERO },
+            GameEvent::LevelComplete,
+        ];
+        assert!(feed("Lvl14", &events, &mut unlocked).is_empty());
+    }
+
+    #[test]
+    fn coverage_threshold() {
+        let mut unlocked = BTreeSet::new();
+        let events = [
+            GameEvent::Painted {
+                splashes: 1,
+                coverage: 0.5,
+            },
+            GameEvent::Painted {
+                splashes: 1,
+                coverage: 0.89,
+            },
+        ];
+        assert!(feed("Lvl01", &events, &mut unlocked).is_empty());
+        let events = [GameEvent::Painted {
+            splashes: 1,
+            coverage: 0.9,
+        }];
+        assert_eq!(feed("Lvl01", &events, &mut unlocked), ["paint_90"]);
+    }
+
+    #[test]
+    fn unlocks_only_once() {
+        let mut unlocked = BTreeSet::new();
+        let events = [GameEvent::Painted {
+            splashes: 1,
+            coverage: 0.95,
+        }; 2];
+        assert_eq!(feed("Lvl01", &events, &mut unlocked), ["paint_90"]);
+        assert!(feed("Lvl01", &events, &mut unlocked).is_empty());
+        assert!(unlocked.contains("paint_90"));
+    }
+
+    #[test]
+    fn toasts_show_one_after_another() {
+        let mut toasts = Toasts::default();
+        toasts.push(&ACHIEVEMENTS[0]);
+        toasts.push(&ACHIEVEMENTS[1]);
+        assert_eq!(toasts.current(), Some(&ACHIEVEMENTS[0]));
+        toasts.update(TOAST_DURATION);
+        assert_eq!(toasts.current(), Some(&ACHIEVEMENTS[1]));
+        toasts.update(TOAST_DURATION);
+        assert_eq!(toasts.current(), None);
+    }
+}
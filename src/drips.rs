@@ -0,0 +1,280 @@
+use crate::level::Level;
+use crate::paint::SplatStamp;
+use glam::{IVec2, Vec2, Vec4, ivec2, vec2};
+use rand::distr::Open01;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// the most drips a single stamp can start
+const MAX_DRIPS_PER_STAMP: u32 = 2;
+/// only wall pixels this close to the stamp center are considered painted enough to drip
+const DRIP_RADIUS: f32 = 0.6;
+/// pixels per second a fresh drip runs
+const DRIP_SPEED: f32 = 8.;
+/// a drip runs slower as its paint runs out, but never slower than this fraction of its speed
+const MIN_SPEED_FRACTION: f32 = 0.2;
+const DRIP_LENGTH: f32 = 6.;
+const MAX_DRIP_LENGTH: f32 = 28.;
+const DRIP_WIDTH: f32 = 2.5;
+
+/// Paint running down the exposed side of a wall
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Drip {
+    /// x of the wall pixel the drip runs down
+    pub x: i32,
+    /// direction of the open air next to the wall, `-1` or `1`
+    pub side: i32,
+    /// the bottom of the drip in world space
+    pub y: f32,
+    pub color: Vec4,
+    /// how far it runs in total
+    pub length: f32,
+    /// how far it has run so far
+    pub run: f32,
+}
+
+impl Drip {
+    /// x of the edge between the wall and the air
+    fn edge(&self) -> f32 {
+        self.x as f32 + 0.5 + self.side as f32 * 0.5
+    }
+
+    fn remaining(&self) -> f32 {
+        1. - self.run / self.length
+    }
+}
+
+/// A rect of paint left behind by a [`Drip`], in world space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DripStroke {
+    pub min: Vec2,
+    pub max: Vec2,
+    pub color: Vec4,
+}
+
+impl DripStroke {
+    /// The four corners in Z order, vertically flipped like [`SplatStamp::corners`]
+    pub fn corners(&self, level_height: u32) -> [Vec2; 4] {
+        let height = level_height as f32;
+        [
+            vec2(self.min.x, height - self.min.y),
+            vec2(self.min.x, height - self.max.y),
+            vec2(self.max.x, height - self.min.y),
+            vec2(self.max.x, height - self.max.y),
+        ]
+    }
+}
+
+/// Whether `pos` is a solid pixel with open air towards `side`
+fn is_wall(level: &Level, pos: IVec2, side: i32) -> bool {
+    level.is_hit(pos) && !level.is_hit(pos + ivec2(side, 0)) && level.contains(pos + ivec2(side, 0))
+}
+
+/// The drips running on a level, advanced by [`crate::entity::game::Game::tick`] which paints their
+/// strokes onto its [`crate::paint::RevealMask`]
+#[derive(Debug, Clone)]
+pub struct Drips {
+    pub drips: Vec<Drip>,
+    rng: StdRng,
+}
+
+impl Drips {
+    pub fn new(level: &Level) -> Self {
+        Self {
+            drips: Vec::new(),
+            rng: StdRng::seed_from_u64(level.hash),
+        }
+    }
+
+    /// Starts drips on the sides of the walls `stamp` painted
+    pub fn spawn(&mut self, level: &Level, stamp: &SplatStamp) {
+        let radius = stamp.size * DRIP_RADIUS;
+        let min = (stamp.pos - radius).floor().as_ivec2();
+        let max = (stamp.pos + radius).ceil().as_ivec2();
+        let mut walls = Vec::new();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let pos = ivec2(x, y);
+                if (pos.as_vec2() + 0.5).distance(stamp.pos) > radius {
+                    continue;
+                }
+                walls.extend(
+                    [-1, 1]
+                        .into_iter()
+                        .filter(|side| is_wall(level, pos, *side))
+                        .map(|side| (pos, side)),
+                );
+            }
+        }
+        if walls.is_empty() {
+            return;
+        }
+
+        let count = self.rng.random_range(0..=MAX_DRIPS_PER_STAMP);
+        for _ in 0..count {
+            let (pos, side) = walls[self.rng.random_range(0..walls.len())];
+            let length =
+                DRIP_LENGTH + (MAX_DRIP_LENGTH - DRIP_LENGTH) * self.rng.sample::<f32, _>(Open01);
+            self.drips.push(Drip {
+                x: pos.x,
+                side,
+                y: pos.y as f32 + 0.5,
+                color: stamp.color,
+                length,
+                run: 0.,
+            });
+        }
+    }
+
+    /// Moves all drips down by `dt` seconds of game time, returning the paint they left behind
+    ///
+    /// A drip stops when it ran out of paint or the wall below it ends.
+    pub fn update(&mut self, level: &Level, dt: f32) -> Vec<DripStroke> {
+        let mut strokes = Vec::with_capacity(self.drips.len());
+        self.drips.retain_mut(|drip| {
+            let speed = DRIP_SPEED * drip.remaining().max(MIN_SPEED_FRACTION);
+            let from = drip.y;
+            let mut to = (from - speed * dt).max(from - (drip.length - drip.run));
+            let mut alive = to > from - (drip.length - drip.run);
+            // check every pixel the drip enters on its way down
+            let mut pixel = from.floor() as i32 - 1;
+            while pixel as f32 + 1. > to {
+                if !is_wall(level, ivec2(drip.x, pixel), drip.side) {
+                    to = pixel as f32 + 1.;
+                    alive = false;
+                    break;
+                }
+                pixel -= 1;
+            }
+            drip.run += from - to;
+            drip.y = to;
+
+            let half_width = DRIP_WIDTH * 0.5 * (0.5 + 0.5 * drip.remaining());
+            // overlap with the last stroke, so slow drips still cover whole pixels
+            strokes.push(DripStroke {
+                min: vec2(drip.edge() - half_width, to - half_width),
+                max: vec2(drip.edge() + half_width, from + half_width),
+                color: drip.color,
+            });
+            alive
+        });
+        strokes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paint::RevealMask;
+    use std::sync::Arc;
+
+    /// a wall at x `16..24` and y `24..56`, standing on the floor
+    fn level() -> Arc<Level> {
+        Level::from_ascii(&[
+            "      ", "  #   ", "  #   ", "  #   ", "  #   ", "      ", "      ", "######",
+        ])
+    }
+
+    fn stamp(pos: Vec2) -> SplatStamp {
+        SplatStamp {
+            pos,
+            size: 10.,
+            rotation: 0.,
+            texture: 0,
+            color: Vec4::new(1., 0., 0., 1.),
+        }
+    }
+
+    #[test]
+    fn drips_start_on_exposed_walls() {
+        let level = level();
+        let mut drips = Drips::new(&level);
+        for _ in 0..20 {
+            drips.spawn(&level, &stamp(vec2(26., 40.)));
+        }
+        assert!(!drips.drips.is_empty());
+        for drip in &drips.drips {
+            assert_eq!((drip.x, drip.side), (23, 1));
+            assert!((34. ..=46.).contains(&drip.y));
+        }
+
+        let mut drips = Drips::new(&level);
+        for _ in 0..20 {
+            drips.spawn(&level, &stamp(vec2(40., 40.)));
+        }
+        assert!(drips.drips.is_empty());
+    }
+
+    #[test]
+    fn drips_stop_where_the_wall_ends() {
+        let level = level();
+        let mut drips = Drips::new(&level);
+        drips.drips.push(Drip {
+            x: 23,
+            side: 1,
+            y: 30.5,
+            color: Vec4::ONE,
+            length: MAX_DRIP_LENGTH,
+            run: 0.,
+        });
+        let strokes = drips.update(&level, 0.1);
+        assert_eq!(drips.drips[0].y, 30.5 - DRIP_SPEED * 0.1);
+        assert_eq!(strokes.len(), 1);
+        // the stroke covers the whole way the drip ran
+        assert!(strokes[0].min.y < drips.drips[0].y && strokes[0].max.y > 30.5);
+
+        let mut lowest = f32::MAX;
+        for _ in 0..100 {
+            for stroke in drips.update(&level, 0.1) {
+                lowest = lowest.min(stroke.min.y + DRIP_WIDTH * 0.5);
+            }
+        }
+        assert!(drips.drips.is_empty());
+        assert!(lowest >= 24.);
+    }
+
+    #[test]
+    fn drips_run_out_of_paint() {
+        let level = level();
+        let mut drips = Drips::new(&level);
+        drips.drips.push(Drip {
+            x: 23,
+            side: 1,
+            y: 54.5,
+            color: Vec4::ONE,
+            length: DRIP_LENGTH,
+            run: 0.,
+        });
+        let mut ticks = 0;
+        while !drips.drips.is_empty() {
+            drips.update(&level, 0.1);
+            ticks += 1;
+        }
+        // slower than a fresh drip, but not slower than the minimum speed
+        let fresh = (DRIP_LENGTH / (DRIP_SPEED * 0.1)).ceil() as u32;
+        let slowest = (DRIP_LENGTH / (DRIP_SPEED * MIN_SPEED_FRACTION * 0.1)).ceil() as u32;
+        assert!((fresh..=slowest).contains(&ticks));
+    }
+
+    #[test]
+    fn strokes_reveal_the_wall() {
+        let level = level();
+        let mut paint = RevealMask::new(&level);
+        paint.stroke(
+            &level,
+            &DripStroke {
+                min: vec2(23., 30.),
+                max: vec2(25., 40.),
+                color: Vec4::new(1., 0., 0., 1.),
+            },
+        );
+        assert!(paint.is_revealed(ivec2(23, 30)));
+        assert!(paint.is_revealed(ivec2(23, 39)));
+        assert!(!paint.is_revealed(ivec2(23, 40)));
+        assert!(!paint.is_revealed(ivec2(22, 35)));
+        // the air next to the wall can't be painted
+        assert!(!paint.is_revealed(ivec2(24, 35)));
+        assert_eq!(paint.revealed_count(), 10);
+        assert_eq!(paint.painted_hue(ivec2(23, 35)), Some(0.));
+    }
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+/// the game speeds that can be chosen, as multipliers of the tick rate
+pub const GAME_SPEEDS: [f32; 3] = [1., 0.75, 0.5];
+
+/// Accessibility options changing how the game plays
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Assists {
+    /// multiplier of the tick rate, one of [`GAME_SPEEDS`]
+    pub game_speed: f32,
+    /// draw faint outlines around the invisible platforms
+    pub outlines: bool,
+    /// respawn where the player last stood instead of at the last checkpoint
+    pub respawn_at_last_standing: bool,
+    /// death tiles don't kill, leaving the level still does
+    pub invincible: bool,
+}
+
+impl Default for Assists {
+    fn default() -> Self {
+        Self {
+            game_speed: 1.,
+            outlines: false,
+            respawn_at_last_standing: false,
+            invincible: false,
+        }
+    }
+}
+
+impl Assists {
+    pub fn any(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// switches to the next of the [`GAME_SPEEDS`]
+    pub fn cycle_game_speed(&mut self) {
+        let current = GAME_SPEEDS
+            .iter()
+            .position(|speed| *speed == self.game_speed)
+            .unwrap_or(0);
+        self.game_speed = GAME_SPEEDS[(current + 1) % GAME_SPEEDS.len()];
+    }
+
+    /// only the options that change the simulation, which replays record
+    pub fn simulated(self) -> Self {
+        Self::from_bits(self.to_bits(), self.game_speed)
+    }
+
+    /// these purely visual options combined with the simulated ones of `other`
+    pub fn with_simulated(self, other: Self) -> Self {
+        Self {
+            outlines: self.outlines,
+            ..other.simulated()
+        }
+    }
+
+    const RESPAWN_AT_LAST_STANDING: u8 = 1 << 0;
+    const INVINCIBLE: u8 = 1 << 1;
+
+    /// the options that change the simulation, as bits for replays
+    pub fn to_bits(self) -> u8 {
+        let mut bits = 0;
+        for (enabled, bit) in [
+            (
+                self.respawn_at_last_standing,
+                Self::RESPAWN_AT_LAST_STANDING,
+            ),
+            (self.invincible, Self::INVINCIBLE),
+        ] {
+            if enabled {
+                bits |= bit;
+            }
+        }
+        bits
+    }
+
+    pub fn from_bits(bits: u8, game_speed: f32) -> Self {
+        Self {
+            game_speed,
+            outlines: false,
+            respawn_at_last_standing: bits & Self::RESPAWN_AT_LAST_STANDING != 0,
+            invincible: bits & Self::INVINCIBLE != 0,
+        }
+    }
+}
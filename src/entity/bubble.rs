@@ -1,5 +1,6 @@
+use crate::entity::game::GameEvent;
 use crate::entity::splash::Splash;
-use crate::level::Level;
+use crate::level::Terrain;
 use glam::{Vec2, Vec4, vec2};
 
 const GRAVITY: Vec2 = vec2(0., 0.1);
@@ -14,22 +15,28 @@ pub struct Bubble {
 }
 
 impl Bubble {
-    pub fn update(&mut self, level: &Level, particles: &mut Vec<Splash>) {
+    pub fn update(
+        &mut self,
+        terrain: Terrain,
+        particles: &mut Vec<Splash>,
+        events: &mut Vec<GameEvent>,
+    ) {
         self.vel *= DAMP;
         self.vel += GRAVITY;
         let new_pos = self.pos + self.vel;
-        if level.is_hit(new_pos.as_ivec2()) {
-            self.pop(particles);
+        if terrain.is_hit(new_pos.as_ivec2()) {
+            self.pop(particles, events);
         } else {
             self.pos = new_pos;
         }
     }
 
-    pub fn pop(&mut self, particles: &mut Vec<Splash>) {
+    pub fn pop(&mut self, particles: &mut Vec<Splash>, events: &mut Vec<GameEvent>) {
         if self.dead {
             return;
         }
         Splash::spawn_many(particles, self.pos, 1., self.color, 10);
+        events.push(GameEvent::BubblePopped { pos: self.pos });
 
         self.dead = true;
     }
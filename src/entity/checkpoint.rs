@@ -0,0 +1,90 @@
+use crate::entity::player::Player;
+use glam::Vec2;
+
+const ACTIVATION_DISTANCE: f32 = 15.;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Checkpoint {
+    pub pos: Vec2,
+    pub active: bool,
+}
+
+impl Checkpoint {
+    pub fn new(pos: Vec2) -> Self {
+        Self { pos, active: false }
+    }
+
+    /// returns true if the checkpoint got activated this tick
+    pub fn update(&mut self, player: &mut Player) -> bool {
+        if !self.active && player.pos.distance(self.pos) < ACTIVATION_DISTANCE {
+            self.active = true;
+            player.respawn_point = self.pos;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entity::assists::Assists;
+    use crate::entity::game::{Game, GameEvent};
+    use crate::entity::input::TickInput;
+    use crate::level::Level;
+
+    fn level() -> Game {
+        let level = Level::from_ascii(&[
+            "................",
+            ".S...C.......P..",
+            "#######X########",
+            "################",
+        ]);
+        Game::new(level, Assists::default())
+    }
+
+    /// Walks right until the player died, returning all events
+    fn walk_into_pit(game: &mut Game) -> Vec<GameEvent> {
+        let right = TickInput {
+            right: true,
+            ..Default::default()
+        };
+        let mut events = Vec::new();
+        for _ in 0..120 {
+            let outcome = game.tick(right);
+            let died = outcome
+                .events
+                .iter()
+                .any(|event| matches!(event, GameEvent::Death { .. }));
+            events.extend(outcome.events);
+            if died {
+                return events;
+            }
+        }
+        panic!("player never died: {events:?}");
+    }
+
+    #[test]
+    fn respawn_at_activated_checkpoint() {
+        let mut game = level();
+        let events = walk_into_pit(&mut game);
+        assert!(events.contains(&GameEvent::CheckpointActivated { index: 0 }));
+        assert!(game.checkpoints[0].active);
+        assert_eq!(game.player.pos, game.checkpoints[0].pos);
+        assert_eq!(game.deaths, 1);
+    }
+
+    #[test]
+    fn restart_deactivates_checkpoints() {
+        let mut game = level();
+        walk_into_pit(&mut game);
+        game.restart(true);
+        assert!(!game.checkpoints[0].active);
+        assert_eq!(game.player.pos, game.level.entry_point.as_vec2());
+
+        // the checkpoint activates again on the next attempt
+        let events = walk_into_pit(&mut game);
+        assert!(events.contains(&GameEvent::CheckpointActivated { index: 0 }));
+        assert_eq!(game.player.pos, game.checkpoints[0].pos);
+    }
+}
@@ -1,69 +1,383 @@
 use crate::delta_time::DeltaTime;
+use crate::drips::{DripStroke, Drips};
+use crate::entity::assists::Assists;
 use crate::entity::bubble::Bubble;
+use crate::entity::checkpoint::Checkpoint;
+use crate::entity::input::TickInput;
+use crate::entity::orb::Orb;
 use crate::entity::player::Player;
 use crate::entity::portal::Portal;
 use crate::entity::splash::Splash;
-use crate::level::Level;
+use crate::level::{Level, Terrain};
+use crate::paint::{RevealMask, SplatStamp};
+use glam::Vec2;
+use std::mem::replace;
 use std::sync::Arc;
 use std::time::Duration;
 
 pub const TIMESTEP: Duration = Duration::from_nanos(16_666_667);
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GameEvent {
+    Jump,
+    Death {
+        pos: Vec2,
+    },
+    BubbleSpawned {
+        pos: Vec2,
+    },
+    BubblePopped {
+        pos: Vec2,
+    },
+    CheckpointActivated {
+        index: usize,
+    },
+    OrbCollected {
+        index: usize,
+        pos: Vec2,
+    },
+    /// paint landed on the level, `coverage` being the revealed fraction afterwards
+    Painted {
+        /// splashes that turned into paint, drips can paint without any
+        splashes: u32,
+        coverage: f32,
+    },
+    LevelComplete,
+}
+
+/// Everything that happened during one or more ticks
+#[derive(Debug, Clone, Default)]
+pub struct TickOutcome {
+    pub events: Vec<GameEvent>,
+    /// paint of splashes that hit something or died of old age, already applied to [`Game::paint`]
+    pub stamps: Vec<SplatStamp>,
+    /// paint left by [`Game::drips`], already applied to [`Game::paint`]
+    pub strokes: Vec<DripStroke>,
+}
+
+impl TickOutcome {
+    pub fn append(&mut self, mut other: TickOutcome) {
+        self.events.append(&mut other.events);
+        self.stamps.append(&mut other.stamps);
+        self.strokes.append(&mut other.strokes);
+    }
+
+    pub fn level_complete(&self) -> bool {
+        self.events.contains(&GameEvent::LevelComplete)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Game {
     pub level: Arc<Level>,
     pub player: Player,
     pub portal: Portal,
+    pub checkpoints: Vec<Checkpoint>,
+    pub orbs: Vec<Orb>,
     pub player_bubble: Option<Bubble>,
     pub splashes: Vec<Splash>,
+    pub paint: RevealMask,
+    /// paint running down the walls it landed on
+    pub drips: Drips,
+    pub assists: Assists,
     pub time_sum: Duration,
+    /// ticks simulated since the level started
+    pub ticks: u32,
+    pub deaths: u32,
+    pub bubbles_used: u32,
 }
 
 impl Game {
-    pub fn new(level: Arc<Level>) -> Self {
+    pub fn new(level: Arc<Level>, assists: Assists) -> Self {
         Self {
             player: Player::new(level.entry_point.as_vec2()),
             portal: Portal::new(level.portal.as_vec2()),
+            checkpoints: level
+                .checkpoints
+                .iter()
+                .map(|pos| Checkpoint::new(pos.as_vec2()))
+                .collect(),
+            orbs: level
+                .orbs
+                .iter()
+                .map(|(pos, kind)| Orb::new(pos.as_vec2(), *kind))
+                .collect(),
             splashes: Vec::new(),
             player_bubble: None,
+            paint: RevealMask::new(&level),
+            drips: Drips::new(&level),
+            assists,
             level,
             time_sum: Duration::ZERO,
+            ticks: 0,
+            deaths: 0,
+            bubbles_used: 0,
         }
     }
 
-    pub fn update(&mut self, dt: DeltaTime) -> Vec<Splash> {
-        self.time_sum += Duration::from_secs_f32(dt.delta_time);
-        let mut despawned_particles = Vec::new();
+    /// Resets the level back to its initial state, including checkpoints, optionally keeping the paint
+    ///
+    /// Kept paint also keeps the deaths and bubbles it took, so restarting can't improve the score.
+    /// The accumulated time carries over, so restarting within [`Self::update`] keeps its pace.
+    pub fn restart(&mut self, keep_paint: bool) {
+        let old = replace(self, Self::new(self.level.clone(), self.assists));
+        self.time_sum = old.time_sum;
+        if keep_paint {
+            self.paint = old.paint;
+            self.drips = old.drips;
+            self.deaths = old.deaths;
+            self.bubbles_used = old.bubbles_used;
+        }
+    }
 
-        while let Some(new) = self.time_sum.checked_sub(TIMESTEP) {
+    /// Advances by as many [`TIMESTEP`]s, stretched by the game speed assist, as fit into the
+    /// accumulated wall-clock time, querying `input` with the state before each tick
+    ///
+    /// Stops at the tick completing the level, dropping the remaining time.
+    pub fn update(
+        &mut self,
+        dt: DeltaTime,
+        mut input: impl FnMut(&Game) -> TickInput,
+    ) -> TickOutcome {
+        self.time_sum += Duration::from_secs_f32(dt.delta_time);
+        let mut outcome = TickOutcome::default();
+        let timestep = TIMESTEP.div_f32(self.assists.game_speed);
+        while let Some(new) = self.time_sum.checked_sub(timestep) {
             self.time_sum = new;
+            let input = input(self);
+            outcome.append(self.tick(input));
+            if outcome.level_complete() {
+                self.time_sum = Duration::ZERO;
+                break;
+            }
+        }
+        outcome
+    }
+
+    /// Advances the simulation by exactly one [`TIMESTEP`]
+    pub fn tick(&mut self, input: TickInput) -> TickOutcome {
+        if input.restart {
+            self.restart(true);
+            return TickOutcome::default();
+        }
+        let mut events = Vec::new();
+        self.ticks += 1;
+        let terrain = Terrain {
+            level: &self.level,
+            paint: &self.paint,
+        };
 
-            if let Some(bubble) = self.player.update(&self.level, &mut self.splashes) {
-                if let Some(mut old) = self.player_bubble.replace(bubble) {
-                    old.pop(&mut self.splashes);
-                }
+        let new_bubble = self.player.update(
+            terrain,
+            self.assists,
+            input,
+            &mut self.splashes,
+            &mut events,
+        );
+        if let Some(bubble) = new_bubble {
+            events.push(GameEvent::BubbleSpawned { pos: bubble.pos });
+            if let Some(mut old) = self.player_bubble.replace(bubble) {
+                old.pop(&mut self.splashes, &mut events);
             }
-            if let Some(bubble) = &mut self.player_bubble {
-                bubble.update(&self.level, &mut self.splashes);
-                if bubble.dead {
-                    self.player_bubble = None;
-                }
+        }
+        if let Some(bubble) = &mut self.player_bubble {
+            bubble.update(terrain, &mut self.splashes, &mut events);
+            if bubble.dead {
+                self.player_bubble = None;
             }
+        }
 
-            self.portal.update(&mut self.player);
+        if let Some(activated) = self
+            .checkpoints
+            .iter_mut()
+            .position(|checkpoint| checkpoint.update(&mut self.player))
+        {
+            for (i, checkpoint) in self.checkpoints.iter_mut().enumerate() {
+                checkpoint.active = i == activated;
+            }
+            events.push(GameEvent::CheckpointActivated { index: activated });
+        }
+        for (index, orb) in self.orbs.iter_mut().enumerate() {
+            if orb.update(&mut self.player, &mut self.splashes) {
+                events.push(GameEvent::OrbCollected {
+                    index,
+                    pos: orb.pos,
+                });
+            }
+        }
+        if self.portal.update(&mut self.player) {
+            events.push(GameEvent::LevelComplete);
+        }
 
-            let mut remove = Vec::new();
-            for (i, particle) in self.splashes.iter_mut().enumerate() {
-                if particle.update(&self.level) {
-                    remove.push(i);
-                }
+        let mut remove = Vec::new();
+        for (i, particle) in self.splashes.iter_mut().enumerate() {
+            if particle.update(terrain) {
+                remove.push(i);
             }
+        }
 
-            for i in remove.into_iter().rev() {
-                let particle = self.splashes.remove(i);
-                despawned_particles.push(particle);
+        let mut despawned_splashes = Vec::new();
+        for i in remove.into_iter().rev() {
+            let particle = self.splashes.remove(i);
+            despawned_splashes.push(particle);
+        }
+
+        for event in &events {
+            match event {
+                GameEvent::Death { .. } => self.deaths += 1,
+                GameEvent::BubbleSpawned { .. } => self.bubbles_used += 1,
+                _ => {}
             }
         }
-        despawned_particles
+        let revealed = self.paint.revealed_count();
+        let stamps = self.paint.paint(&self.level, &despawned_splashes);
+        for stamp in &stamps {
+            self.drips.spawn(&self.level, stamp);
+        }
+        let strokes = self.drips.update(&self.level, TIMESTEP.as_secs_f32());
+        for stroke in &strokes {
+            self.paint.stroke(&self.level, stroke);
+        }
+        if !stamps.is_empty() || self.paint.revealed_count() != revealed {
+            events.push(GameEvent::Painted {
+                splashes: stamps.len() as u32,
+                coverage: self.paint.coverage(),
+            });
+        }
+        TickOutcome {
+            events,
+            stamps,
+            strokes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(rows: &[&str]) -> Game {
+        Game::new(Level::from_ascii(rows), Assists::default())
+    }
+
+    /// Ticks until `until` returns true for an outcome, returning all events
+    fn tick_until(
+        game: &mut Game,
+        input: TickInput,
+        until: impl Fn(&TickOutcome) -> bool,
+    ) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        for _ in 0..300 {
+            let outcome = game.tick(input);
+            let done = until(&outcome);
+            events.extend(outcome.events);
+            if done {
+                return events;
+            }
+        }
+        panic!("condition never met: {events:?}");
+    }
+
+    fn died(outcome: &TickOutcome) -> bool {
+        outcome
+            .events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Death { .. }))
+    }
+
+    #[test]
+    fn jump() {
+        // high enough to not jump out of the level
+        let mut rows = vec!["........"; 24];
+        rows.extend([".S......", "########"]);
+        let mut game = game(&rows);
+        // fall onto the ground first
+        for _ in 0..10 {
+            game.tick(TickInput::default());
+        }
+        let ground = game.player.pos.y;
+        let jump = TickInput {
+            jump: true,
+            ..Default::default()
+        };
+        assert_eq!(game.tick(jump).events, vec![GameEvent::Jump]);
+        let mut peak = ground;
+        for _ in 0..10 {
+            game.tick(jump);
+            peak = peak.max(game.player.pos.y);
+        }
+        assert!(peak > ground + 20., "peak {peak} ground {ground}");
+
+        // holding jump doesn't jump again after landing
+        for _ in 0..60 {
+            assert!(!game.tick(jump).events.contains(&GameEvent::Jump));
+        }
+        assert_eq!(game.deaths, 0);
+    }
+
+    #[test]
+    fn death_respawns_at_entry_point() {
+        let mut game = game(&["........", ".S......", "XXXXXXXX"]);
+        let events = tick_until(&mut game, TickInput::default(), died);
+        assert!(matches!(events[0], GameEvent::Death { .. }));
+        assert_eq!(game.player.pos, game.level.entry_point.as_vec2());
+        assert_eq!(game.deaths, 1);
+        assert!(!game.splashes.is_empty());
+    }
+
+    #[test]
+    fn level_complete() {
+        let mut game = game(&["........", ".S...P..", "########"]);
+        let right = TickInput {
+            right: true,
+            ..Default::default()
+        };
+        tick_until(&mut game, right, TickOutcome::level_complete);
+        assert!(game.player.hidden);
+        assert!(game.portal.jump_to_next_level());
+    }
+
+    #[test]
+    fn update_stops_at_level_complete() {
+        let mut game = game(&["........", ".S...P..", "########"]);
+        game.portal.tick = Some(1);
+        let dt = DeltaTime {
+            delta_time: 1.,
+            since_start: 0.,
+        };
+        let mut ticks = 0;
+        let outcome = game.update(dt, |_| {
+            ticks += 1;
+            TickInput::default()
+        });
+        assert!(outcome.level_complete());
+        assert_eq!(ticks, 1);
+        assert_eq!(game.ticks, 1);
+    }
+
+    #[test]
+    fn restart_input_keeps_paint() {
+        let mut game = game(&["#......#", "#.S....#", "########"]);
+        let bubble = TickInput {
+            bubble: true,
+            ..Default::default()
+        };
+        game.tick(bubble);
+        tick_until(&mut game, TickInput::default(), |outcome| {
+            !outcome.stamps.is_empty()
+        });
+        let revealed = game.paint.revealed_count();
+        assert!(revealed > 0);
+
+        let restart = TickInput {
+            restart: true,
+            ..Default::default()
+        };
+        assert!(game.tick(restart).events.is_empty());
+        assert_eq!(game.paint.revealed_count(), revealed);
+        assert_eq!(game.bubbles_used, 1);
+        assert_eq!(game.ticks, 0);
+        assert_eq!(game.player.pos, game.level.entry_point.as_vec2());
+        assert!(game.splashes.is_empty());
     }
 }
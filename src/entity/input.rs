@@ -0,0 +1,46 @@
+/// The state of all player controls during a single tick
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TickInput {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    pub bubble: bool,
+    /// restarts the level keeping its paint instead of simulating the tick, see [`Game::restart`]
+    ///
+    /// [`Game::restart`]: crate::entity::game::Game::restart
+    pub restart: bool,
+}
+
+impl TickInput {
+    const LEFT: u8 = 1 << 0;
+    const RIGHT: u8 = 1 << 1;
+    const JUMP: u8 = 1 << 2;
+    const BUBBLE: u8 = 1 << 3;
+    const RESTART: u8 = 1 << 4;
+
+    pub fn to_bits(self) -> u8 {
+        let mut bits = 0;
+        for (pressed, bit) in [
+            (self.left, Self::LEFT),
+            (self.right, Self::RIGHT),
+            (self.jump, Self::JUMP),
+            (self.bubble, Self::BUBBLE),
+            (self.restart, Self::RESTART),
+        ] {
+            if pressed {
+                bits |= bit;
+            }
+        }
+        bits
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            left: bits & Self::LEFT != 0,
+            right: bits & Self::RIGHT != 0,
+            jump: bits & Self::JUMP != 0,
+            bubble: bits & Self::BUBBLE != 0,
+            restart: bits & Self::RESTART != 0,
+        }
+    }
+}
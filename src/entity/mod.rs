@@ -1,5 +1,9 @@
+pub mod assists;
 pub mod bubble;
+pub mod checkpoint;
 pub mod game;
+pub mod input;
+pub mod orb;
 pub mod player;
 pub mod portal;
 pub mod splash;
@@ -0,0 +1,147 @@
+use crate::entity::player::Player;
+use crate::entity::splash::Splash;
+use glam::Vec2;
+
+const PICKUP_DISTANCE: f32 = 12.;
+const PICKUP_SPLASHES: u32 = 12;
+const PICKUP_SPLASH_SPEED: f32 = 3.;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OrbKind {
+    /// sets the player's hue and stops it from cycling
+    Lock { hue: f32 },
+    /// adds to the player's hue, keeping it locked if it was
+    Shift { hue: f32 },
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Orb {
+    pub pos: Vec2,
+    pub kind: OrbKind,
+    pub collected: bool,
+}
+
+impl Orb {
+    pub fn new(pos: Vec2, kind: OrbKind) -> Self {
+        Self {
+            pos,
+            kind,
+            collected: false,
+        }
+    }
+
+    /// the hue a player with `hue` ends up with after picking up this orb
+    pub fn target_hue(&self, hue: f32) -> f32 {
+        match self.kind {
+            OrbKind::Lock { hue } => hue,
+            OrbKind::Shift { hue: shift } => (hue + shift) % 1.,
+        }
+    }
+
+    /// returns true if the orb got collected this tick, bursting into paint of the new color
+    pub fn update(&mut self, player: &mut Player, particles: &mut Vec<Splash>) -> bool {
+        if self.collected || player.pos.distance(self.pos) >= PICKUP_DISTANCE {
+            return false;
+        }
+        self.collected = true;
+        player.hsv_hue = self.target_hue(player.hsv_hue);
+        if let OrbKind::Lock { .. } = self.kind {
+            player.hue_locked = true;
+        }
+        Splash::spawn_many(
+            particles,
+            self.pos,
+            PICKUP_SPLASH_SPEED,
+            player.color(),
+            PICKUP_SPLASHES,
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::assists::Assists;
+    use crate::entity::game::{Game, GameEvent};
+    use crate::entity::input::TickInput;
+    use crate::level::Level;
+    use glam::vec2;
+
+    fn player(hue: f32, hue_locked: bool) -> Player {
+        let mut player = Player::new(vec2(10., 10.));
+        player.hsv_hue = hue;
+        player.hue_locked = hue_locked;
+        player
+    }
+
+    #[test]
+    fn target_hue_wraps() {
+        let lock = Orb::new(Vec2::ZERO, OrbKind::Lock { hue: 0.25 });
+        assert_eq!(lock.target_hue(0.9), 0.25);
+        let shift = Orb::new(Vec2::ZERO, OrbKind::Shift { hue: 0.25 });
+        assert_eq!(shift.target_hue(0.5), 0.75);
+        assert!((shift.target_hue(0.875) - 0.125).abs() < 1e-6);
+    }
+
+    #[test]
+    fn lock_collects_once() {
+        let mut orb = Orb::new(vec2(15., 10.), OrbKind::Lock { hue: 0.25 });
+        let mut player = player(0.9, false);
+        let mut particles = Vec::new();
+        assert!(orb.update(&mut player, &mut particles));
+        assert!(orb.collected);
+        assert_eq!(player.hsv_hue, 0.25);
+        assert!(player.hue_locked);
+        assert_eq!(particles.len(), PICKUP_SPLASHES as usize);
+
+        player.hsv_hue = 0.;
+        assert!(!orb.update(&mut player, &mut particles));
+        assert_eq!(player.hsv_hue, 0.);
+    }
+
+    #[test]
+    fn shift_keeps_the_lock() {
+        let mut particles = Vec::new();
+        for locked in [false, true] {
+            let mut orb = Orb::new(vec2(10., 10.), OrbKind::Shift { hue: 0.5 });
+            let mut player = player(0.25, locked);
+            assert!(orb.update(&mut player, &mut particles));
+            assert_eq!(player.hsv_hue, 0.75);
+            assert_eq!(player.hue_locked, locked);
+        }
+    }
+
+    #[test]
+    fn out_of_reach() {
+        let mut orb = Orb::new(
+            vec2(10. + PICKUP_DISTANCE, 10.),
+            OrbKind::Lock { hue: 0.25 },
+        );
+        let mut player = player(0.9, false);
+        assert!(!orb.update(&mut player, &mut Vec::new()));
+        assert!(!orb.collected);
+    }
+
+    #[test]
+    fn locked_hue_stops_cycling() {
+        let level = Level::from_ascii(&["........", ".S.O....", "########"]);
+        assert_eq!(level.orbs[0].1, OrbKind::Lock { hue: 0.5 });
+        let mut game = Game::new(level, Assists::default());
+        let right = TickInput {
+            right: true,
+            ..TickInput::default()
+        };
+        let collected = (0..20).any(|_| {
+            game.tick(right)
+                .events
+                .iter()
+                .any(|event| matches!(event, GameEvent::OrbCollected { index: 0, .. }))
+        });
+        assert!(collected);
+        for _ in 0..10 {
+            game.tick(TickInput::default());
+        }
+        assert_eq!(game.player.hsv_hue, 0.5);
+    }
+}
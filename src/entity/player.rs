@@ -1,10 +1,11 @@
+use crate::entity::assists::Assists;
 use crate::entity::bubble::Bubble;
+use crate::entity::game::GameEvent;
+use crate::entity::input::TickInput;
 use crate::entity::splash::Splash;
 use crate::hsv2rgb::hsv2rgb;
-use crate::level::Level;
+use crate::level::Terrain;
 use glam::{Vec2, Vec3, Vec4, vec2};
-use winit::event::{ElementState, KeyEvent};
-use winit::keyboard::{KeyCode, PhysicalKey};
 
 const GRAVITY: Vec2 = vec2(0.0, -1.1);
 const SPEED_X: f32 = 5.5;
@@ -19,15 +20,15 @@ pub struct Player {
     pub pos: Vec2,
     pub vel: Vec2,
     pub hsv_hue: f32,
+    /// a locked hue does not cycle anymore
+    pub hue_locked: bool,
     pub hidden: bool,
+    pub respawn_point: Vec2,
+    /// where the player stood on the ground last
+    pub last_standing: Vec2,
 
     on_ground: bool,
-    left_pressed: bool,
-    right_pressed: bool,
-    old_jump_pressed: bool,
-    jump_pressed: bool,
-    old_bubble_spawn_pressed: bool,
-    bubble_spawn_pressed: bool,
+    old_input: TickInput,
     // false = pointed left
     pointed_right: bool,
 }
@@ -38,72 +39,59 @@ impl Player {
             pos,
             vel: vec2(0.0, -1.0),
             hsv_hue: 0.,
+            hue_locked: false,
             hidden: false,
+            respawn_point: pos,
+            last_standing: pos,
             on_ground: false,
-            left_pressed: false,
-            right_pressed: false,
-            old_jump_pressed: false,
-            jump_pressed: false,
-            old_bubble_spawn_pressed: false,
-            bubble_spawn_pressed: false,
+            old_input: TickInput::default(),
             pointed_right: true,
         }
     }
 
     pub fn color(&self) -> Vec4 {
-        Vec4::from((hsv2rgb(Vec3::new(self.hsv_hue, 1., 1.)), 1.))
+        Self::hue_color(self.hsv_hue)
     }
 
-    pub fn handle_key_event(&mut self, event: &KeyEvent) {
-        let pressed = event.state == ElementState::Pressed;
-        match event.physical_key {
-            PhysicalKey::Code(KeyCode::KeyA | KeyCode::ArrowLeft) => {
-                self.left_pressed = pressed;
-                if pressed {
-                    self.pointed_right = false;
-                }
-            }
-            PhysicalKey::Code(KeyCode::KeyD | KeyCode::ArrowRight) => {
-                self.right_pressed = pressed;
-                if pressed {
-                    self.pointed_right = true;
-                }
-            }
-            PhysicalKey::Code(KeyCode::Space | KeyCode::KeyW | KeyCode::ArrowUp) => {
-                self.jump_pressed = pressed;
-            }
-            PhysicalKey::Code(
-                KeyCode::ShiftLeft
-                | KeyCode::ShiftRight
-                | KeyCode::KeyE
-                | KeyCode::KeyQ
-                | KeyCode::ArrowDown
-                | KeyCode::KeyS,
-            ) => {
-                self.bubble_spawn_pressed = pressed;
-            }
-            _ => (),
-        }
+    pub fn hue_color(hue: f32) -> Vec4 {
+        Vec4::from((hsv2rgb(Vec3::new(hue, 1., 1.)), 1.))
     }
 
-    pub fn update(&mut self, level: &Level, particles: &mut Vec<Splash>) -> Option<Bubble> {
-        self.hsv_hue = (self.hsv_hue + HSV_HUE_SPEED) % 1.;
+    pub fn update(
+        &mut self,
+        terrain: Terrain,
+        assists: Assists,
+        input: TickInput,
+        particles: &mut Vec<Splash>,
+        events: &mut Vec<GameEvent>,
+    ) -> Option<Bubble> {
+        if !self.hue_locked {
+            self.hsv_hue = (self.hsv_hue + HSV_HUE_SPEED) % 1.;
+        }
+
+        if input.left && !self.old_input.left {
+            self.pointed_right = false;
+        }
+        if input.right && !self.old_input.right {
+            self.pointed_right = true;
+        }
 
-        if self.left_pressed {
+        if input.left {
             self.vel.x = -SPEED_X;
-        } else if self.right_pressed {
+        } else if input.right {
             self.vel.x = SPEED_X;
         } else {
             self.vel.x *= DAMP_X;
         }
 
-        if self.jump_pressed && !self.old_jump_pressed && self.on_ground {
+        if input.jump && !self.old_input.jump && self.on_ground {
             self.vel.y = JUMP_Y;
+            events.push(GameEvent::Jump);
         } else {
             self.vel.y *= DAMP_Y;
         }
 
-        let bubble = if self.bubble_spawn_pressed && !self.old_bubble_spawn_pressed {
+        let bubble = if input.bubble && !self.old_input.bubble {
             Some(Bubble {
                 pos: self.pos,
                 vel: if self.pointed_right {
@@ -120,7 +108,7 @@ impl Player {
 
         self.vel += GRAVITY;
         let new_pos = self.pos + self.vel;
-        if level.is_hit(new_pos.as_ivec2()) {
+        if terrain.is_hit(new_pos.as_ivec2()) {
             self.vel = Vec2::ZERO;
             self.pos.x = new_pos.x; // horribly broken
             self.on_ground = true; // not necessarily true
@@ -129,14 +117,27 @@ impl Player {
             self.on_ground = false;
         }
 
-        if level.is_death(new_pos.as_ivec2()) {
+        if self.on_ground {
+            self.last_standing = self.pos;
+        }
+
+        let dies = if assists.invincible {
+            !terrain.level.contains(new_pos.as_ivec2())
+        } else {
+            terrain.is_death(new_pos.as_ivec2())
+        };
+        if dies {
             Splash::spawn_many(particles, self.pos, 2., self.color(), 25);
-            self.pos = level.entry_point.as_vec2();
+            events.push(GameEvent::Death { pos: self.pos });
+            self.pos = if assists.respawn_at_last_standing {
+                self.last_standing
+            } else {
+                self.respawn_point
+            };
             self.vel = Vec2::ZERO;
         }
 
-        self.old_jump_pressed = self.jump_pressed;
-        self.old_bubble_spawn_pressed = self.bubble_spawn_pressed;
+        self.old_input = input;
         bubble
     }
 }
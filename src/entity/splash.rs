@@ -1,4 +1,4 @@
-use crate::level::Level;
+use crate::level::Terrain;
 use glam::{Vec2, Vec4, vec2};
 use std::f32::consts::E;
 
@@ -27,11 +27,11 @@ impl Splash {
     }
 
     // returns true if collided
-    pub fn update(&mut self, level: &Level) -> bool {
+    pub fn update(&mut self, terrain: Terrain) -> bool {
         self.vel *= DAMP;
         self.vel += GRAVITY;
         self.age += 1;
         self.pos += self.vel;
-        level.is_hit(self.pos.as_ivec2()) || self.age > MAX_AGE
+        terrain.is_hit(self.pos.as_ivec2()) || self.age > MAX_AGE
     }
 }
@@ -0,0 +1,765 @@
+use crate::achievements::{AchievementTracker, Toasts};
+use crate::delta_time::DeltaTime;
+use crate::entity::assists::Assists;
+use crate::entity::game::{Game, TickOutcome};
+use crate::entity::input::TickInput;
+use crate::ghost::Ghost;
+use crate::level::Level;
+use crate::replay::{Replay, ReplayPlayback};
+use crate::save::{SaveData, SaveStore};
+use crate::score::LevelScore;
+use crate::speedrun::SpeedrunTimer;
+use anyhow::ensure;
+use std::mem::take;
+use std::sync::Arc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Screen {
+    Title,
+    Playing,
+    Paused,
+    LevelComplete,
+    Finished,
+    Stats,
+    LevelSelect,
+    Assists,
+}
+
+/// Menu inputs, decoupled from any windowing events
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MenuAction {
+    Confirm,
+    Back,
+    /// opens the stats screen from the title screen
+    Stats,
+    /// opens the level select from the title screen
+    LevelSelect,
+    /// opens the assist options from the title screen or while paused
+    Assists,
+    /// moves a menu selection, `dy` being positive downwards
+    Move {
+        dx: i32,
+        dy: i32,
+    },
+}
+
+/// the number of levels in a row of the level select
+pub const LEVEL_SELECT_COLUMNS: usize = 5;
+
+/// the rows of the assists screen
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AssistOption {
+    GameSpeed,
+    Outlines,
+    RespawnAtLastStanding,
+    Invincible,
+}
+
+impl AssistOption {
+    pub const ALL: [Self; 4] = [
+        Self::GameSpeed,
+        Self::Outlines,
+        Self::RespawnAtLastStanding,
+        Self::Invincible,
+    ];
+
+    pub fn toggle(self, assists: &mut Assists) {
+        match self {
+            Self::GameSpeed => assists.cycle_game_speed(),
+            Self::Outlines => assists.outlines = !assists.outlines,
+            Self::RespawnAtLastStanding => {
+                assists.respawn_at_last_standing = !assists.respawn_at_last_standing
+            }
+            Self::Invincible => assists.invincible = !assists.invincible,
+        }
+    }
+}
+
+/// Side effects the owner of a [`GameFlow`] has to carry out
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FlowCommand {
+    /// `game` was replaced, the level has to be (re)loaded into the renderer
+    LoadLevel,
+    /// the attempt restarted on the same level keeping its paint, only the ghost has to be reloaded
+    RestartLevel,
+    Exit,
+}
+
+#[derive(Debug)]
+pub struct GameFlow {
+    pub levels: Vec<Arc<Level>>,
+    pub level_idx: usize,
+    pub screen: Screen,
+    pub game: Game,
+    /// inputs of the current level attempt
+    pub recording: Replay,
+    /// trajectory of the current level attempt
+    pub trajectory: Ghost,
+    /// while set, inputs come from the replay instead of the player
+    pub playback: Option<ReplayPlayback>,
+    /// restarts the level keeping its paint on the next tick, so the recording contains the restart
+    pub restart_pending: bool,
+    pub timer: SpeedrunTimer,
+    /// score of the last completed level
+    pub score: Option<LevelScore>,
+    /// whether the stats got exported since opening the stats screen
+    pub stats_exported: bool,
+    pub achievements: AchievementTracker,
+    pub toasts: Toasts,
+    /// the highlighted level of the level select
+    pub selected_level: usize,
+    /// the highlighted row of the assists screen, see [`AssistOption::ALL`]
+    pub selected_assist: usize,
+    /// the screen the assists screen returns to
+    pub assists_return: Screen,
+    pub store: Box<dyn SaveStore>,
+    pub save: SaveData,
+}
+
+impl GameFlow {
+    /// Continues from the progress in `store`
+    pub fn new(levels: Vec<Arc<Level>>, mut store: Box<dyn SaveStore>) -> Self {
+        assert!(!levels.is_empty(), "no levels to play");
+        let save = SaveData::load_or_default(store.as_mut());
+        let level_idx = save.current_level.min(levels.len() - 1);
+        Self {
+            game: Game::new(levels[level_idx].clone(), save.settings.assists),
+            recording: Replay::new(level_idx, &levels[level_idx], save.settings.assists),
+            trajectory: Ghost::new(&levels[level_idx]),
+            achievements: AchievementTracker::new(&levels[level_idx]),
+            levels,
+            level_idx,
+            screen: Screen::Title,
+            playback: None,
+            restart_pending: false,
+            timer: SpeedrunTimer::default(),
+            score: None,
+            stats_exported: false,
+            toasts: Toasts::default(),
+            selected_level: level_idx,
+            selected_assist: 0,
+            assists_return: Screen::Title,
+            store,
+            save,
+        }
+    }
+
+    pub fn save_progress(&mut self) {
+        if let Err(err) = self.save.save(self.store.as_mut()) {
+            eprintln!("failed to save progress: {err:#}");
+        }
+    }
+
+    pub fn is_last_level(&self) -> bool {
+        self.level_idx + 1 >= self.levels.len()
+    }
+
+    pub fn handle_action(&mut self, action: MenuAction) -> Option<FlowCommand> {
+        match (self.screen, action) {
+            (Screen::Title, MenuAction::Confirm) => {
+                self.screen = Screen::Playing;
+                self.timer.start_run(self.level_idx);
+                None
+            }
+            (Screen::Title, MenuAction::Back) => {
+                self.save_progress();
+                Some(FlowCommand::Exit)
+            }
+            (Screen::Title, MenuAction::Stats) => {
+                self.screen = Screen::Stats;
+                self.stats_exported = false;
+                None
+            }
+            (Screen::Stats, MenuAction::Confirm) => {
+                match self.save.stats.export(self.store.as_mut()) {
+                    Ok(()) => self.stats_exported = true,
+                    Err(err) => eprintln!("failed to export stats: {err:#}"),
+                }
+                None
+            }
+            (Screen::Stats, MenuAction::Back | MenuAction::Stats) => {
+                self.screen = Screen::Title;
+                None
+            }
+            (Screen::Title, MenuAction::LevelSelect) => {
+                self.screen = Screen::LevelSelect;
+                self.selected_level = self.level_idx;
+                None
+            }
+            (Screen::LevelSelect, MenuAction::Move { dx, dy }) => {
+                // moving sideways stops at the edges of the row instead of wrapping into the next one
+                let columns = LEVEL_SELECT_COLUMNS as i32;
+                let column = (self.selected_level as i32 % columns + dx).clamp(0, columns - 1);
+                let selected = (self.selected_level as i32 / columns + dy) * columns + column;
+                if (0..self.levels.len() as i32).contains(&selected) {
+                    self.selected_level = selected as usize;
+                }
+                None
+            }
+            (Screen::LevelSelect, MenuAction::Confirm) => {
+                if !self.save.is_unlocked(self.selected_level) {
+                    return None;
+                }
+                self.screen = Screen::Playing;
+                self.timer.start_run(self.selected_level);
+                Some(self.load_level(self.selected_level))
+            }
+            (Screen::LevelSelect, MenuAction::Back | MenuAction::LevelSelect) => {
+                self.screen = Screen::Title;
+                None
+            }
+            (Screen::Title | Screen::Paused, MenuAction::Assists) => {
+                self.assists_return = self.screen;
+                self.screen = Screen::Assists;
+                None
+            }
+            (Screen::Assists, MenuAction::Move { dx: 0, dy }) => {
+                let selected = self.selected_assist as i32 + dy;
+                if (0..AssistOption::ALL.len() as i32).contains(&selected) {
+                    self.selected_assist = selected as usize;
+                }
+                None
+            }
+            (Screen::Assists, MenuAction::Confirm | MenuAction::Move { .. }) => {
+                AssistOption::ALL[self.selected_assist].toggle(&mut self.save.settings.assists);
+                None
+            }
+            (Screen::Assists, MenuAction::Back | MenuAction::Assists) => {
+                self.screen = self.assists_return;
+                let command = self.apply_assists();
+                self.save_progress();
+                command
+            }
+            (
+                _,
+                MenuAction::Stats
+                | MenuAction::LevelSelect
+                | MenuAction::Assists
+                | MenuAction::Move { .. },
+            ) => None,
+            (Screen::Playing, MenuAction::Back) => {
+                self.screen = Screen::Paused;
+                self.save_progress();
+                None
+            }
+            (Screen::Playing, MenuAction::Confirm) => None,
+            (Screen::Paused, MenuAction::Back) => {
+                self.screen = Screen::Playing;
+                None
+            }
+            (Screen::Paused, MenuAction::Confirm) => {
+                self.screen = Screen::Title;
+                Some(self.load_level(self.level_idx))
+            }
+            (Screen::LevelComplete, _) => {
+                if self.is_last_level() {
+                    self.screen = Screen::Finished;
+                    None
+                } else {
+                    self.screen = Screen::Playing;
+                    self.timer.start_level();
+                    Some(self.load_level(self.level_idx + 1))
+                }
+            }
+            (Screen::Finished, _) => {
+                self.screen = Screen::Title;
+                Some(self.load_level(0))
+            }
+        }
+    }
+
+    /// Restarts the current level, `keep_paint` decides whether the revealed paint survives the restart
+    pub fn restart_level(&mut self, keep_paint: bool) -> Option<FlowCommand> {
+        match self.screen {
+            Screen::Playing | Screen::Paused => {
+                self.screen = Screen::Playing;
+                if keep_paint {
+                    self.playback = None;
+                    self.restart_pending = true;
+                    Some(FlowCommand::RestartLevel)
+                } else {
+                    Some(self.load_level(self.level_idx))
+                }
+            }
+            Screen::Title
+            | Screen::LevelComplete
+            | Screen::Finished
+            | Screen::Stats
+            | Screen::LevelSelect
+            | Screen::Assists => None,
+        }
+    }
+
+    /// Restarts the replay's level and plays it back instead of taking player input, once the replay
+    /// runs out the player takes over as after a restart keeping the paint
+    pub fn start_replay(&mut self, replay: Replay) -> anyhow::Result<FlowCommand> {
+        let level_idx = replay.level_idx as usize;
+        ensure!(
+            self.levels
+                .get(level_idx)
+                .is_some_and(|level| replay.matches(level_idx, level)),
+            "replay was recorded on a different level"
+        );
+        let command = self.load_level(level_idx);
+        self.game.assists = self.game.assists.with_simulated(replay.assists);
+        self.recording.assists = replay.assists;
+        self.playback = Some(ReplayPlayback::new(replay));
+        self.timer.start_run(level_idx);
+        self.timer.full_run = false;
+        self.screen = Screen::Playing;
+        Ok(command)
+    }
+
+    /// Advances the game while [`Screen::Playing`], `input` is ignored during replay playback
+    pub fn update(&mut self, dt: DeltaTime, input: TickInput) -> TickOutcome {
+        self.toasts.update(dt.delta_time);
+        if self.screen != Screen::Playing {
+            return TickOutcome::default();
+        }
+
+        let recording = &mut self.recording;
+        let trajectory = &mut self.trajectory;
+        let playback = &mut self.playback;
+        let restart_pending = &mut self.restart_pending;
+        let timer = &mut self.timer;
+        let stats = &mut self.save.stats;
+        let outcome = self.game.update(dt, |game| {
+            timer.tick(game.assists.simulated().any());
+            if playback.is_none() {
+                stats.tick(game.assists.any());
+            }
+            let input = match playback.as_mut().map(ReplayPlayback::next_input) {
+                Some(Some(input)) => input,
+                Some(None) => {
+                    *playback = None;
+                    TickInput {
+                        restart: true,
+                        ..input
+                    }
+                }
+                None => TickInput {
+                    restart: take(restart_pending),
+                    ..input
+                },
+            };
+            recording.push(input);
+            if input.restart {
+                trajectory.positions.clear();
+            } else {
+                trajectory.positions.push(game.player.pos);
+            }
+            input
+        });
+        if self.playback.is_none() {
+            self.save.stats.record(self.level_idx, &outcome.events);
+        }
+        let recorded = self.is_recorded();
+        if recorded {
+            let mut unlocked_any = false;
+            for event in &outcome.events {
+                for achievement in self
+                    .achievements
+                    .observe(event, &mut self.save.achievements)
+                {
+                    self.toasts.push(achievement);
+                    unlocked_any = true;
+                }
+            }
+            if unlocked_any && !outcome.level_complete() {
+                self.save_progress();
+            }
+        }
+        if outcome.level_complete() {
+            self.screen = Screen::LevelComplete;
+            self.score = Some(LevelScore::new(&self.game));
+            // the trajectory starts at the last restart keeping paint while the timer doesn't, such
+            // ghosts would race against a head start
+            let restarted = self.recording.inputs.iter().any(|input| input.restart);
+            if recorded
+                && !restarted
+                && let Err(err) = self
+                    .trajectory
+                    .save_if_best(self.store.as_mut(), &self.game.level)
+            {
+                eprintln!("failed to save ghost: {err:#}");
+            }
+            if recorded {
+                self.timer.split(self.level_idx, &mut self.save.bests);
+            }
+            if self.is_last_level() {
+                if recorded {
+                    self.timer.finish_run(&mut self.save.bests);
+                }
+                self.save.current_level = 0;
+            } else {
+                self.save.unlock_level(self.level_idx + 1);
+                self.save.current_level = self.level_idx + 1;
+            }
+            self.save_progress();
+        }
+        outcome
+    }
+
+    fn load_level(&mut self, level_idx: usize) -> FlowCommand {
+        self.level_idx = level_idx;
+        self.game = Game::new(self.levels[level_idx].clone(), self.save.settings.assists);
+        self.score = None;
+        self.achievements.start_attempt(&self.levels[level_idx]);
+        self.reset_recording();
+        FlowCommand::LoadLevel
+    }
+
+    /// Hands changed assists to the game, reloading the level if they change the simulation so
+    /// that the recording stays faithful
+    fn apply_assists(&mut self) -> Option<FlowCommand> {
+        let assists = self.save.settings.assists;
+        if assists.simulated().any() {
+            self.timer.full_run = false;
+        }
+        if assists.to_bits() != self.game.assists.to_bits() {
+            return Some(self.load_level(self.level_idx));
+        }
+        self.game.assists = assists;
+        self.recording.assists = assists.simulated();
+        None
+    }
+
+    /// whether the current attempt counts for bests, ghosts and achievements, which played back
+    /// runs and runs with assists changing the simulation don't
+    fn is_recorded(&self) -> bool {
+        self.playback.is_none() && !self.game.assists.simulated().any()
+    }
+
+    fn reset_recording(&mut self) {
+        self.recording = Replay::new(self.level_idx, &self.game.level, self.game.assists);
+        self.trajectory = Ghost::new(&self.game.level);
+        self.playback = None;
+        self.restart_pending = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::game::TIMESTEP;
+    use crate::save::MemorySaveStore;
+
+    fn flow() -> GameFlow {
+        GameFlow::new(
+            Level::load_file_tree().unwrap(),
+            Box::new(MemorySaveStore::default()),
+        )
+    }
+
+    /// Lets the portal jump to the next level on the next tick
+    fn complete_level(flow: &mut GameFlow) {
+        flow.game.portal.tick = Some(1);
+        let dt = DeltaTime {
+            delta_time: 0.1,
+            since_start: 0.,
+        };
+        assert!(flow.update(dt, TickInput::default()).level_complete());
+        assert_eq!(flow.screen, Screen::LevelComplete);
+    }
+
+    #[test]
+    fn title_playing_paused_title() {
+        let mut flow = flow();
+        assert_eq!(flow.screen, Screen::Title);
+        assert_eq!(flow.handle_action(MenuAction::Confirm), None);
+        assert_eq!(flow.screen, Screen::Playing);
+        flow.handle_action(MenuAction::Back);
+        assert_eq!(flow.screen, Screen::Paused);
+        flow.handle_action(MenuAction::Back);
+        assert_eq!(flow.screen, Screen::Playing);
+        flow.handle_action(MenuAction::Back);
+        assert_eq!(
+            flow.handle_action(MenuAction::Confirm),
+            Some(FlowCommand::LoadLevel)
+        );
+        assert_eq!(flow.screen, Screen::Title);
+        assert_eq!(
+            flow.handle_action(MenuAction::Back),
+            Some(FlowCommand::Exit)
+        );
+    }
+
+    #[test]
+    fn level_complete_loads_next_level() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::Confirm);
+        complete_level(&mut flow);
+        assert!(flow.save.is_unlocked(1));
+        assert_eq!(flow.save.current_level, 1);
+        assert!(flow.store.load(crate::save::SAVE_KEY).unwrap().is_some());
+
+        assert_eq!(
+            flow.handle_action(MenuAction::Confirm),
+            Some(FlowCommand::LoadLevel)
+        );
+        assert_eq!(flow.screen, Screen::Playing);
+        assert_eq!(flow.level_idx, 1);
+        assert_eq!(flow.game.ticks, 0);
+    }
+
+    #[test]
+    fn level_select_moves_within_rows() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::LevelSelect);
+        assert_eq!(flow.selected_level, 0);
+        flow.handle_action(MenuAction::Move { dx: -1, dy: 0 });
+        assert_eq!(flow.selected_level, 0);
+        for _ in 0..LEVEL_SELECT_COLUMNS + 2 {
+            flow.handle_action(MenuAction::Move { dx: 1, dy: 0 });
+        }
+        assert_eq!(flow.selected_level, LEVEL_SELECT_COLUMNS - 1);
+        flow.handle_action(MenuAction::Move { dx: 0, dy: 1 });
+        assert_eq!(flow.selected_level, 2 * LEVEL_SELECT_COLUMNS - 1);
+        flow.handle_action(MenuAction::Move { dx: -1, dy: -1 });
+        assert_eq!(flow.selected_level, LEVEL_SELECT_COLUMNS - 2);
+        flow.handle_action(MenuAction::Move { dx: 0, dy: -1 });
+        assert_eq!(flow.selected_level, LEVEL_SELECT_COLUMNS - 2);
+
+        // the last row isn't full, moving past its last level stays put
+        let last = flow.levels.len() - 1;
+        assert_ne!(last % LEVEL_SELECT_COLUMNS, LEVEL_SELECT_COLUMNS - 1);
+        flow.selected_level = last;
+        flow.handle_action(MenuAction::Move { dx: 1, dy: 0 });
+        assert_eq!(flow.selected_level, last);
+        flow.handle_action(MenuAction::Move { dx: 0, dy: 1 });
+        assert_eq!(flow.selected_level, last);
+    }
+
+    #[test]
+    fn locked_levels_cannot_be_started() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::LevelSelect);
+        flow.handle_action(MenuAction::Move { dx: 1, dy: 0 });
+        assert!(!flow.save.is_unlocked(1));
+        assert_eq!(flow.handle_action(MenuAction::Confirm), None);
+        assert_eq!(flow.screen, Screen::LevelSelect);
+
+        flow.save.unlock_level(1);
+        assert_eq!(
+            flow.handle_action(MenuAction::Confirm),
+            Some(FlowCommand::LoadLevel)
+        );
+        assert_eq!(flow.screen, Screen::Playing);
+        assert_eq!(flow.level_idx, 1);
+    }
+
+    #[test]
+    fn last_level_finishes() {
+        let mut flow = flow();
+        let last = flow.levels.len() - 1;
+        flow.save.unlock_level(last);
+        flow.handle_action(MenuAction::LevelSelect);
+        flow.selected_level = last;
+        flow.handle_action(MenuAction::Confirm);
+        assert_eq!(flow.level_idx, last);
+
+        complete_level(&mut flow);
+        assert_eq!(flow.handle_action(MenuAction::Confirm), None);
+        assert_eq!(flow.screen, Screen::Finished);
+        assert_eq!(flow.save.current_level, 0);
+        assert_eq!(
+            flow.handle_action(MenuAction::Confirm),
+            Some(FlowCommand::LoadLevel)
+        );
+        assert_eq!(flow.screen, Screen::Title);
+        assert_eq!(flow.level_idx, 0);
+    }
+
+    #[test]
+    fn restarts_keep_the_level_clock() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::Confirm);
+        flow.timer.level_ticks = 100;
+        flow.timer.run_ticks = 100;
+        flow.restart_level(true);
+        assert_eq!(flow.timer.level_ticks, 100);
+        flow.restart_level(false);
+        assert_eq!(flow.timer.level_ticks, 100);
+
+        complete_level(&mut flow);
+        assert_eq!(flow.save.bests.level(0), Some(101));
+        flow.handle_action(MenuAction::Confirm);
+        assert_eq!(flow.timer.level_ticks, 0);
+        assert_eq!(flow.timer.run_ticks, 101);
+    }
+
+    #[test]
+    fn playback_sets_no_bests() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::Confirm);
+        let mut replay = flow.recording.clone();
+        replay.inputs = vec![TickInput::default(); 10];
+        flow.start_replay(replay).unwrap();
+        complete_level(&mut flow);
+        assert_eq!(flow.save.bests, Default::default());
+        assert!(flow.timer.splits.is_empty());
+    }
+
+    #[test]
+    fn playback_hands_over_when_it_runs_out() {
+        let mut flow = flow();
+        flow.save.settings.assists.outlines = true;
+        flow.handle_action(MenuAction::Confirm);
+        let mut replay = flow.recording.clone();
+        replay.assists.invincible = true;
+        replay.inputs = vec![TickInput::default(); 2];
+        flow.start_replay(replay).unwrap();
+        assert!(flow.game.assists.outlines);
+        assert!(flow.game.assists.invincible);
+
+        let dt = DeltaTime {
+            delta_time: TIMESTEP.as_secs_f32() * 2.5,
+            since_start: 0.,
+        };
+        flow.update(dt, TickInput::default());
+        assert!(flow.playback.is_some());
+        flow.update(dt, TickInput::default());
+        assert!(flow.playback.is_none());
+        assert!(flow.recording.inputs[2].restart);
+    }
+
+    #[test]
+    fn assisted_runs_set_no_bests() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::Confirm);
+        flow.handle_action(MenuAction::Back);
+        flow.handle_action(MenuAction::Assists);
+        flow.selected_assist = AssistOption::ALL
+            .iter()
+            .position(|option| *option == AssistOption::Invincible)
+            .unwrap();
+        flow.handle_action(MenuAction::Confirm);
+        assert_eq!(
+            flow.handle_action(MenuAction::Back),
+            Some(FlowCommand::LoadLevel)
+        );
+        assert_eq!(flow.screen, Screen::Paused);
+        assert!(flow.game.assists.invincible);
+        assert!(flow.recording.assists.invincible);
+
+        flow.handle_action(MenuAction::Back);
+        complete_level(&mut flow);
+        assert_eq!(flow.save.bests, Default::default());
+        assert!(flow.save.achievements.is_empty());
+        assert!(Ghost::load_best(flow.store.as_ref(), &flow.game.level).is_none());
+    }
+
+    #[test]
+    fn assisted_levels_void_the_full_run() {
+        let mut flow = flow();
+        flow.save.bests.full_run = Some(1_000_000);
+        flow.handle_action(MenuAction::Confirm);
+        assert!(flow.timer.full_run);
+        let toggle_invincible = |flow: &mut GameFlow| {
+            flow.handle_action(MenuAction::Back);
+            flow.handle_action(MenuAction::Assists);
+            flow.selected_assist = AssistOption::ALL
+                .iter()
+                .position(|option| *option == AssistOption::Invincible)
+                .unwrap();
+            flow.handle_action(MenuAction::Confirm);
+            flow.handle_action(MenuAction::Back);
+            flow.handle_action(MenuAction::Back);
+        };
+        toggle_invincible(&mut flow);
+        assert!(!flow.timer.full_run);
+        complete_level(&mut flow);
+        flow.handle_action(MenuAction::Confirm);
+        // turning the assist off again doesn't make the run count
+        toggle_invincible(&mut flow);
+        assert!(!flow.game.assists.invincible);
+        while !flow.is_last_level() {
+            complete_level(&mut flow);
+            flow.handle_action(MenuAction::Confirm);
+        }
+        complete_level(&mut flow);
+        flow.handle_action(MenuAction::Confirm);
+        assert_eq!(flow.screen, Screen::Finished);
+        assert_eq!(flow.timer.total, None);
+        assert_eq!(flow.save.bests.full_run, Some(1_000_000));
+    }
+
+    #[test]
+    fn visual_assists_keep_the_attempt() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::Confirm);
+        flow.update(
+            DeltaTime {
+                delta_time: 1.,
+                since_start: 0.,
+            },
+            TickInput::default(),
+        );
+        let ticks = flow.game.ticks;
+        flow.handle_action(MenuAction::Back);
+        flow.handle_action(MenuAction::Assists);
+        flow.handle_action(MenuAction::Confirm);
+        flow.selected_assist = 1;
+        flow.handle_action(MenuAction::Confirm);
+        assert_eq!(flow.handle_action(MenuAction::Back), None);
+        assert_eq!(flow.game.ticks, ticks);
+        assert!(flow.game.assists.outlines);
+        assert!(flow.game.assists.game_speed < 1.);
+    }
+
+    #[test]
+    fn restarts_keeping_paint_save_no_ghost() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::Confirm);
+        flow.update(
+            DeltaTime {
+                delta_time: 1.,
+                since_start: 0.,
+            },
+            TickInput::default(),
+        );
+        flow.restart_level(true);
+        flow.update(
+            DeltaTime {
+                delta_time: 0.02,
+                since_start: 0.,
+            },
+            TickInput::default(),
+        );
+        complete_level(&mut flow);
+        assert!(flow.save.bests.level(0).is_some());
+        assert!(Ghost::load_best(flow.store.as_ref(), &flow.game.level).is_none());
+
+        flow.handle_action(MenuAction::Confirm);
+        complete_level(&mut flow);
+        assert!(Ghost::load_best(flow.store.as_ref(), &flow.game.level).is_some());
+    }
+
+    #[test]
+    fn recording_replays_restarts() {
+        let mut flow = flow();
+        flow.handle_action(MenuAction::Confirm);
+        let dt = DeltaTime {
+            delta_time: 1.,
+            since_start: 0.,
+        };
+        let bubble = TickInput {
+            bubble: true,
+            ..TickInput::default()
+        };
+        flow.update(dt, bubble);
+        assert_eq!(flow.restart_level(true), Some(FlowCommand::RestartLevel));
+        flow.update(dt, TickInput::default());
+        flow.update(dt, bubble);
+        assert!(flow.recording.inputs.iter().any(|input| input.restart));
+        assert_eq!(flow.trajectory.ticks(), flow.game.ticks as usize);
+
+        let mut game = Game::new(flow.game.level.clone(), flow.recording.assists);
+        for input in &flow.recording.inputs {
+            game.tick(*input);
+        }
+        assert_eq!(game.paint.painted, flow.game.paint.painted);
+        assert_eq!(game.player.pos, flow.game.player.pos);
+        assert_eq!(game.ticks, flow.game.ticks);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::level::Level;
+use crate::save::SaveStore;
+use anyhow::{bail, ensure};
+use glam::{Vec2, vec2};
+
+const MAGIC: [u8; 4] = *b"CBGH";
+pub const GHOST_VERSION: u16 = 1;
+
+/// The player position at every tick of a level attempt
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ghost {
+    pub level_hash: u64,
+    pub positions: Vec<Vec2>,
+}
+
+impl Ghost {
+    pub fn new(level: &Level) -> Self {
+        Self {
+            level_hash: level.hash,
+            positions: Vec::new(),
+        }
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// the position at `tick`, the ghost stays at its last position once its run is over
+    pub fn position(&self, tick: u32) -> Option<Vec2> {
+        self.positions
+            .get(tick as usize)
+            .or(self.positions.last())
+            .copied()
+    }
+
+    fn key(level_hash: u64) -> String {
+        format!("ghost_{level_hash:016x}")
+    }
+
+    pub fn load_best(store: &dyn SaveStore, level: &Level) -> Option<Self> {
+        Self::from_bytes(&store.load(&Self::key(level.hash)).ok()??)
+            .ok()
+            .filter(|ghost| ghost.level_hash == level.hash)
+    }
+
+    /// Stores this ghost if it is faster than the stored best, returns true if it was stored
+    pub fn save_if_best(&self, store: &mut dyn SaveStore, level: &Level) -> anyhow::Result<bool> {
+        if Self::load_best(store, level).is_some_and(|best| best.ticks() <= self.ticks()) {
+            return Ok(false);
+        }
+        store.store(&Self::key(self.level_hash), &self.to_bytes())?;
+        Ok(true)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(18 + self.positions.len() * 8);
+        bytes.extend(MAGIC);
+        bytes.extend(GHOST_VERSION.to_le_bytes());
+        bytes.extend(self.level_hash.to_le_bytes());
+        bytes.extend((self.positions.len() as u32).to_le_bytes());
+        for pos in &self.positions {
+            bytes.extend(pos.x.to_le_bytes());
+            bytes.extend(pos.y.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        ensure!(
+            bytes.len() >= 18 && bytes[0..4] == MAGIC,
+            "not a ghost file"
+        );
+        let version = u16::from_le_bytes(bytes[4..6].try_into()?);
+        if version != GHOST_VERSION {
+            bail!("unsupported ghost version {version}, expected {GHOST_VERSION}");
+        }
+        let level_hash = u64::from_le_bytes(bytes[6..14].try_into()?);
+        let len = u32::from_le_bytes(bytes[14..18].try_into()?) as usize;
+        let data = &bytes[18..];
+        ensure!(
+            len.checked_mul(8) == Some(data.len()),
+            "ghost file is truncated"
+        );
+        let positions = data
+            .chunks_exact(8)
+            .map(|pos| {
+                vec2(
+                    f32::from_le_bytes(pos[0..4].try_into().unwrap()),
+                    f32::from_le_bytes(pos[4..8].try_into().unwrap()),
+                )
+            })
+            .collect();
+        Ok(Self {
+            level_hash,
+            positions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::MemorySaveStore;
+
+    fn ghost(ticks: usize) -> Ghost {
+        Ghost {
+            level_hash: 0xfeed_beef,
+            positions: (0..ticks)
+                .map(|i| vec2(i as f32, -0.5 * i as f32))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        for ghost in [ghost(0), ghost(1), ghost(300)] {
+            let bytes = ghost.to_bytes();
+            assert_eq!(bytes.len(), 18 + ghost.ticks() * 8);
+            assert_eq!(Ghost::from_bytes(&bytes).unwrap(), ghost);
+        }
+    }
+
+    #[test]
+    fn reject_invalid_bytes() {
+        let bytes = ghost(3).to_bytes();
+        assert!(Ghost::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Ghost::from_bytes(&bytes[..10]).is_err());
+        assert!(Ghost::from_bytes(b"CBRP and some more bytes").is_err());
+
+        let mut version = bytes.clone();
+        version[4..6].copy_from_slice(&(GHOST_VERSION + 1).to_le_bytes());
+        assert!(Ghost::from_bytes(&version).is_err());
+
+        // a length that overflows when counted in bytes
+        let mut len = bytes.clone();
+        len[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Ghost::from_bytes(&len).is_err());
+    }
+
+    #[test]
+    fn only_faster_runs_are_stored() {
+        let level = Level::from_ascii(&["S.P", "###"]);
+        let mut store = MemorySaveStore::default();
+        let ghost = |ticks| Ghost {
+            level_hash: level.hash,
+            ..self::ghost(ticks)
+        };
+        assert!(Ghost::load_best(&store, &level).is_none());
+        assert!(ghost(20).save_if_best(&mut store, &level).unwrap());
+        assert!(!ghost(20).save_if_best(&mut store, &level).unwrap());
+        assert!(!ghost(25).save_if_best(&mut store, &level).unwrap());
+        assert!(ghost(10).save_if_best(&mut store, &level).unwrap());
+        assert_eq!(Ghost::load_best(&store, &level), Some(ghost(10)));
+    }
+
+    #[test]
+    fn position_stays_at_the_end() {
+        let ghost = ghost(3);
+        assert_eq!(ghost.position(1), Some(vec2(1., -0.5)));
+        assert_eq!(ghost.position(100), Some(vec2(2., -1.)));
+        assert_eq!(self::ghost(0).position(0), None);
+    }
+}
@@ -31,3 +31,55 @@ pub fn hsv2rgb(c: Vec3) -> Vec3 {
     );
     c.z * Vec3::lerp(Vec3::splat(1.0), rgb, c.y)
 }
+
+/// The hue of an RGB color in `0..1`, the inverse of [`hsv2rgb`] for saturated colors
+pub fn rgb2hue(c: Vec3) -> f32 {
+    let max = c.max_element();
+    let delta = max - c.min_element();
+    if delta <= 0. {
+        return 0.;
+    }
+    let hue = if max == c.x {
+        (c.y - c.z) / delta
+    } else if max == c.y {
+        (c.z - c.x) / delta + 2.
+    } else {
+        (c.x - c.y) / delta + 4.
+    };
+    (hue / 6.).rem_euclid(1.)
+}
+
+/// distance between two hues in `0..1`, wrapping around
+pub fn hue_distance(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(1.);
+    d.min(1. - d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb2hue_inverts_hsv2rgb() {
+        for i in 0..100 {
+            let hue = i as f32 / 100.;
+            let rgb = hsv2rgb(vec3(hue, 1., 1.));
+            let distance = hue_distance(rgb2hue(rgb), hue);
+            assert!(distance < 1e-4, "hue {hue} came back as {}", rgb2hue(rgb));
+        }
+        assert_eq!(rgb2hue(vec3(1., 0., 0.)), 0.);
+        assert_eq!(rgb2hue(vec3(0., 0., 1.)), 2. / 3.);
+        // grays have no hue
+        assert_eq!(rgb2hue(Vec3::splat(0.5)), 0.);
+    }
+
+    #[test]
+    fn hue_distance_wraps_around() {
+        assert_eq!(hue_distance(0.25, 0.25), 0.);
+        assert!((hue_distance(0.25, 0.5) - 0.25).abs() < 1e-6);
+        assert!((hue_distance(0.95, 0.05) - 0.1).abs() < 1e-6);
+        assert!((hue_distance(0.05, 0.95) - 0.1).abs() < 1e-6);
+        assert!((hue_distance(0., 0.5) - 0.5).abs() < 1e-6);
+        assert!((hue_distance(1.2, 0.1) - 0.1).abs() < 1e-6);
+    }
+}
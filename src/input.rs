@@ -0,0 +1,41 @@
+use crate::entity::input::TickInput;
+use winit::event::{ElementState, KeyEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+/// Tracks the keyboard state and maps it onto [`TickInput`]s
+#[derive(Debug, Copy, Clone, Default)]
+pub struct KeyboardInput {
+    input: TickInput,
+}
+
+impl KeyboardInput {
+    pub fn handle_key_event(&mut self, event: &KeyEvent) {
+        let pressed = event.state == ElementState::Pressed;
+        match event.physical_key {
+            PhysicalKey::Code(KeyCode::KeyA | KeyCode::ArrowLeft) => {
+                self.input.left = pressed;
+            }
+            PhysicalKey::Code(KeyCode::KeyD | KeyCode::ArrowRight) => {
+                self.input.right = pressed;
+            }
+            PhysicalKey::Code(KeyCode::Space | KeyCode::KeyW | KeyCode::ArrowUp) => {
+                self.input.jump = pressed;
+            }
+            PhysicalKey::Code(
+                KeyCode::ShiftLeft
+                | KeyCode::ShiftRight
+                | KeyCode::KeyE
+                | KeyCode::KeyQ
+                | KeyCode::ArrowDown
+                | KeyCode::KeyS,
+            ) => {
+                self.input.bubble = pressed;
+            }
+            _ => (),
+        }
+    }
+
+    pub fn tick_input(&self) -> TickInput {
+        self.input
+    }
+}
@@ -1,37 +1,111 @@
+use crate::entity::orb::OrbKind;
+use crate::hsv2rgb::{hsv2rgb, hue_distance};
+use crate::paint::RevealMask;
+use anyhow::{Context, bail};
 use bevy_math::{Rect, URect};
-use glam::{IVec2, UVec2, uvec2};
+use glam::{IVec2, UVec2, Vec3, uvec2};
 use image::{GrayImage, ImageFormat, ImageReader, Luma, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::Cursor;
 use std::sync::Arc;
 
 const ENTRY_POINT: Rgba<u8> = Rgba([0, 99, 0, 255]);
 const PORTAL: Rgba<u8> = Rgba([0, 98, 0, 255]);
+const CHECKPOINT: Rgba<u8> = Rgba([0, 97, 0, 255]);
+/// green of a `(hue, 96, 0)` orb locking the player's hue to `hue / 256`
+const ORB_LOCK: u8 = 96;
+/// green of a `(shift, 95, 0)` orb shifting the player's hue by `shift / 256`
+const ORB_SHIFT: u8 = 95;
+/// green of a `(hue, 94, 0)` platform that is only solid once painted close to `hue / 256`
+const GATE: u8 = 94;
 const DEATH: Rgba<u8> = Rgba([0, 0, 100, 255]);
 const COLLISION: Rgba<u8> = Rgba([255, 255, 255, 255]);
 // const COLLISION: Rgba<u8> = Rgba([0, 0, 255, 255]);
 
 const COLLISION_LUMA: Luma<u8> = Luma([255]);
 const DEATH_LUMA: Luma<u8> = Luma([1]);
+const GATE_LUMA: Luma<u8> = Luma([128]);
+
+const THUMBNAIL_BACKGROUND: Rgba<u8> = Rgba([20, 20, 30, 255]);
+const THUMBNAIL_DEATH: Rgba<u8> = Rgba([200, 40, 40, 255]);
+const OUTLINE: Rgba<u8> = Rgba([255, 255, 255, 60]);
+
+/// how far the painted hue of a gated platform may be off for it to become solid
+pub const GATE_HUE_TOLERANCE: f32 = 0.06;
+
+/// [`LevelMeta`] of every level, keyed by the level's file name without extension
+const LEVEL_META: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/meta.json"));
+
+/// A tiling image drawn behind the level
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackgroundLayer {
+    /// name of one of [`crate::rendering::background_renderer::BACKGROUND_TEXTURES`]
+    pub texture: String,
+    /// how much the layer moves with the camera, `0` sticks to the screen and `1` to the level
+    pub scroll: [f32; 2],
+    /// multiplied with the texture
+    pub tint: [f32; 4],
+    /// pixels per second the layer moves on its own
+    pub auto_scroll: [f32; 2],
+}
+
+impl Default for BackgroundLayer {
+    fn default() -> Self {
+        Self {
+            texture: String::new(),
+            scroll: [0.5, 0.5],
+            tint: [1., 1., 1., 1.],
+            auto_scroll: [0., 0.],
+        }
+    }
+}
+
+/// Everything about a level that isn't part of its image
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelMeta {
+    /// file name of the level without extension, the key of its meta, empty for levels not shipped
+    #[serde(skip)]
+    pub name: String,
+    /// drawn back to front
+    pub background: Vec<BackgroundLayer>,
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct Level {
     pub size: UVec2,
     pub image: RgbaImage,
     pub collision_map: GrayImage,
+    /// the hue required by each gated pixel of the `collision_map`, as in the legend
+    pub gate_hues: GrayImage,
     pub entry_point: UVec2,
     pub portal: UVec2,
+    pub checkpoints: Vec<UVec2>,
+    pub orbs: Vec<(UVec2, OrbKind)>,
+    /// FNV-1a hash of the level image, identifies a level independently of its position in the level list
+    pub hash: u64,
+    pub meta: LevelMeta,
 }
 
 impl Level {
-    pub fn load_from_binary(bytes: &[u8]) -> anyhow::Result<Arc<Level>> {
+    pub fn load_from_binary(bytes: &[u8], meta: LevelMeta) -> anyhow::Result<Arc<Level>> {
         let image = ImageReader::with_format(Cursor::new(bytes), ImageFormat::Png)
             .decode()?
             .flipv()
             .into_rgba8();
+        Ok(Self::from_image(image, meta))
+    }
 
+    /// Builds a level from an image using the legend colors, its first row being the bottom of the level
+    pub fn from_image(image: RgbaImage, meta: LevelMeta) -> Arc<Level> {
         let mut collision_map = GrayImage::new(image.width(), image.height());
+        let mut gate_hues = GrayImage::new(image.width(), image.height());
         let mut entry_point = UVec2::ZERO;
         let mut portal = UVec2::ZERO;
+        let mut checkpoints = Vec::new();
+        let mut orbs = Vec::new();
 
         for y in 0..image.height() {
             for x in 0..image.width() {
@@ -41,6 +115,23 @@ impl Level {
                 match pixel {
                     ENTRY_POINT => entry_point = pos,
                     PORTAL => portal = pos,
+                    CHECKPOINT => checkpoints.push(pos),
+                    Rgba([hue, ORB_LOCK, 0, _]) => orbs.push((
+                        pos,
+                        OrbKind::Lock {
+                            hue: hue as f32 / 256.,
+                        },
+                    )),
+                    Rgba([shift, ORB_SHIFT, 0, _]) => orbs.push((
+                        pos,
+                        OrbKind::Shift {
+                            hue: shift as f32 / 256.,
+                        },
+                    )),
+                    Rgba([hue, GATE, 0, _]) => {
+                        collision_map.put_pixel(pos.x, pos.y, GATE_LUMA);
+                        gate_hues.put_pixel(pos.x, pos.y, Luma([hue]));
+                    }
                     COLLISION => collision_map.put_pixel(pos.x, pos.y, COLLISION_LUMA),
                     DEATH => collision_map.put_pixel(pos.x, pos.y, DEATH_LUMA),
                     _ => {}
@@ -48,35 +139,133 @@ impl Level {
             }
         }
 
-        Ok(Arc::new(Self {
+        Arc::new(Self {
             size: uvec2(image.width(), image.height()),
+            hash: fnv1a(image.as_raw()),
             image,
             collision_map,
+            gate_hues,
             entry_point,
             portal,
-        }))
+            checkpoints,
+            orbs,
+            meta,
+        })
+    }
+
+    /// A level drawn as text for tests, top row first, every character being a block of
+    /// [`Self::ASCII_SCALE`] pixels
+    ///
+    /// `#` is solid, `X` kills, `=` is gated by the hue `0.5`, and `S`, `P`, `C` and `O` put the entry
+    /// point, portal, a checkpoint or an orb locking the hue to `0.5` at the center of their block.
+    #[cfg(test)]
+    pub fn from_ascii(rows: &[&str]) -> Arc<Level> {
+        let scale = Self::ASCII_SCALE;
+        let size = uvec2(rows[0].len() as u32, rows.len() as u32) * scale;
+        let mut image = RgbaImage::new(size.x, size.y);
+        for (row, line) in rows.iter().rev().enumerate() {
+            for (column, char) in line.chars().enumerate() {
+                let min = uvec2(column as u32, row as u32) * scale;
+                let center = min + scale / 2;
+                match char {
+                    '#' | 'X' | '=' => {
+                        let color = match char {
+                            '#' => COLLISION,
+                            'X' => DEATH,
+                            _ => Rgba([128, GATE, 0, 255]),
+                        };
+                        for y in min.y..min.y + scale {
+                            for x in min.x..min.x + scale {
+                                image.put_pixel(x, y, color);
+                            }
+                        }
+                    }
+                    'S' => image.put_pixel(center.x, center.y, ENTRY_POINT),
+                    'P' => image.put_pixel(center.x, center.y, PORTAL),
+                    'C' => image.put_pixel(center.x, center.y, CHECKPOINT),
+                    'O' => image.put_pixel(center.x, center.y, Rgba([128, ORB_LOCK, 0, 255])),
+                    _ => {}
+                }
+            }
+        }
+        Self::from_image(image, LevelMeta::default())
     }
 
+    #[cfg(test)]
+    pub const ASCII_SCALE: u32 = 8;
+
     pub fn load_file_tree() -> anyhow::Result<Vec<Arc<Level>>> {
-        [
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl01.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl02.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl03.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl04.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl05.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl06.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl07.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl08.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl09.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl10.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl11.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl12.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl13.png")).as_slice(),
-            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl14.png")).as_slice(),
-        ]
-        .iter()
-        .map(|a| Self::load_from_binary(a))
-        .collect::<Result<Vec<_>, _>>()
+        let files = [
+            (
+                "Lvl01",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl01.png")).as_slice(),
+            ),
+            (
+                "Lvl02",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl02.png")).as_slice(),
+            ),
+            (
+                "Lvl03",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl03.png")).as_slice(),
+            ),
+            (
+                "Lvl04",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl04.png")).as_slice(),
+            ),
+            (
+                "Lvl05",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl05.png")).as_slice(),
+            ),
+            (
+                "Lvl06",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl06.png")).as_slice(),
+            ),
+            (
+                "Lvl07",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl07.png")).as_slice(),
+            ),
+            (
+                "Lvl08",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl08.png")).as_slice(),
+            ),
+            (
+                "Lvl09",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl09.png")).as_slice(),
+            ),
+            (
+                "Lvl10",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl10.png")).as_slice(),
+            ),
+            (
+                "Lvl11",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl11.png")).as_slice(),
+            ),
+            (
+                "Lvl12",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl12.png")).as_slice(),
+            ),
+            (
+                "Lvl13",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl13.png")).as_slice(),
+            ),
+            (
+                "Lvl14",
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/levels/Lvl14.png")).as_slice(),
+            ),
+        ];
+        let names = files.map(|(name, _)| name);
+        let meta = parse_meta(LEVEL_META, &names).context("invalid levels/meta.json")?;
+        files
+            .iter()
+            .map(|(name, bytes)| {
+                let meta = LevelMeta {
+                    name: name.to_string(),
+                    ..meta.get(*name).cloned().unwrap_or_default()
+                };
+                Self::load_from_binary(bytes, meta)
+                    .with_context(|| format!("failed to load level {name}"))
+            })
+            .collect()
     }
 
     pub fn collision_rect(&self, rect: Rect) -> bool {
@@ -118,7 +307,180 @@ impl Level {
         }
     }
 
+    pub fn contains(&self, pos: IVec2) -> bool {
+        pos.cmpge(IVec2::ZERO).all() && pos.as_uvec2().cmplt(self.size).all()
+    }
+
+    /// Faint outlines of all solid and gated platforms, flipped like the level
+    pub fn outline(&self) -> RgbaImage {
+        let is_platform = |pos: IVec2| {
+            self.contains(pos)
+                && matches!(
+                    *self.collision_map.get_pixel(pos.x as u32, pos.y as u32),
+                    COLLISION_LUMA | GATE_LUMA
+                )
+        };
+        RgbaImage::from_fn(self.size.x, self.size.y, |x, y| {
+            let pos = IVec2::new(x as i32, y as i32);
+            let edge = is_platform(pos)
+                && [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+                    .into_iter()
+                    .any(|dir| !is_platform(pos + dir));
+            if edge { OUTLINE } else { Rgba([0; 4]) }
+        })
+    }
+
+    /// the hue a gated platform pixel has to be painted with to become solid
+    pub fn gate_hue(&self, pos: IVec2) -> Option<f32> {
+        if pos.x < 0 || pos.y < 0 {
+            return None;
+        }
+        let (x, y) = (pos.x as u32, pos.y as u32);
+        (self.collision_map.get_pixel_checked(x, y) == Some(&GATE_LUMA))
+            .then(|| self.gate_hues.get_pixel(x, y).0[0] as f32 / 256.)
+    }
+
+    /// the number of solid and gated pixels, which are the ones that can be painted
+    pub fn collision_pixels(&self) -> usize {
+        self.collision_map
+            .pixels()
+            .filter(|p| **p == COLLISION_LUMA || **p == GATE_LUMA)
+            .count()
+    }
+
+    /// A downscaled picture of the collision map fitting into `max_size`, flipped like the level
+    pub fn thumbnail(&self, max_size: UVec2) -> RgbaImage {
+        let factor = (self.size.as_vec2() / max_size.as_vec2())
+            .ceil()
+            .max_element()
+            .max(1.) as u32;
+        let size = (self.size + factor - 1) / factor;
+        let mut thumbnail = RgbaImage::from_pixel(size.x, size.y, THUMBNAIL_BACKGROUND);
+        for (x, y, pixel) in self.collision_map.enumerate_pixels() {
+            let color = match *pixel {
+                COLLISION_LUMA => COLLISION,
+                GATE_LUMA => {
+                    let hue = self.gate_hues.get_pixel(x, y).0[0] as f32 / 256.;
+                    let rgb = hsv2rgb(Vec3::new(hue, 0.6, 1.)) * 255.;
+                    Rgba([rgb.x as u8, rgb.y as u8, rgb.z as u8, 255])
+                }
+                DEATH_LUMA => THUMBNAIL_DEATH,
+                _ => continue,
+            };
+            let target = thumbnail.get_pixel_mut(x / factor, y / factor);
+            // solid platforms take precedence over anything else within the same thumbnail pixel
+            if *target != COLLISION {
+                *target = color;
+            }
+        }
+        thumbnail
+    }
+
     pub fn extent(&self) -> UVec2 {
         UVec2::new(self.collision_map.width(), self.collision_map.height())
     }
 }
+
+/// A level together with the paint on it, which decides whether gated platforms are solid
+#[derive(Debug, Copy, Clone)]
+pub struct Terrain<'a> {
+    pub level: &'a Level,
+    pub paint: &'a RevealMask,
+}
+
+impl Terrain<'_> {
+    pub fn is_hit(&self, pos: IVec2) -> bool {
+        self.level.is_hit(pos) || self.is_gate_open(pos)
+    }
+
+    pub fn is_death(&self, pos: IVec2) -> bool {
+        self.level.is_death(pos)
+    }
+
+    /// whether the pixel is a gated platform painted in its hue
+    pub fn is_gate_open(&self, pos: IVec2) -> bool {
+        self.level.gate_hue(pos).is_some_and(|gate| {
+            self.paint
+                .painted_hue(pos)
+                .is_some_and(|hue| hue_distance(hue, gate) <= GATE_HUE_TOLERANCE)
+        })
+    }
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Parses the [`LevelMeta`] of the levels called `names`, rejecting meta of levels that don't exist
+fn parse_meta(json: &str, names: &[&str]) -> anyhow::Result<HashMap<String, LevelMeta>> {
+    let meta: HashMap<String, LevelMeta> = serde_json::from_str(json)?;
+    if let Some(unknown) = meta.keys().find(|key| !names.contains(&key.as_str())) {
+        bail!("meta of unknown level {unknown}");
+    }
+    Ok(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::player::Player;
+    use crate::paint::SplatStamp;
+    use glam::ivec2;
+
+    #[test]
+    fn meta_of_unknown_levels_is_rejected() {
+        let names = ["Lvl01", "Lvl02"];
+        let meta = parse_meta(r#"{"Lvl02": {"background": []}}"#, &names).unwrap();
+        assert_eq!(meta["Lvl02"], LevelMeta::default());
+        assert!(parse_meta(r#"{"lvl01": {}}"#, &names).is_err());
+        assert!(parse_meta(r#"{"Lvl03": {}}"#, &names).is_err());
+    }
+
+    #[test]
+    fn shipped_meta_names_shipped_levels() {
+        Level::load_file_tree().unwrap();
+    }
+
+    #[test]
+    fn gates_open_when_painted_in_their_hue() {
+        let level = Level::from_ascii(&["....", "=##=", "...."]);
+        let gate = ivec2(4, 12);
+        assert_eq!(level.gate_hue(gate), Some(0.5));
+        assert_eq!(level.gate_hue(ivec2(12, 12)), None);
+        assert_eq!(level.collision_pixels(), 4 * 8 * 8);
+
+        let paint = |hue: f32| {
+            let mut paint = RevealMask::new(&level);
+            paint.stamp(
+                &level,
+                &SplatStamp {
+                    pos: gate.as_vec2() + 0.5,
+                    size: 6.,
+                    rotation: 0.,
+                    texture: 0,
+                    color: Player::hue_color(hue),
+                },
+            );
+            paint
+        };
+        let unpainted = RevealMask::new(&level);
+        let terrain = |paint| Terrain {
+            level: &level,
+            paint,
+        };
+        assert!(!terrain(&unpainted).is_hit(gate));
+        assert!(terrain(&unpainted).is_hit(ivec2(12, 12)));
+
+        let matching = paint(0.5 + GATE_HUE_TOLERANCE * 0.5);
+        assert!(matching.is_revealed(gate));
+        assert!(terrain(&matching).is_hit(gate));
+        // the other gate block wasn't painted
+        assert!(!terrain(&matching).is_hit(ivec2(28, 12)));
+
+        let wrong = paint(0.5 + GATE_HUE_TOLERANCE * 2.);
+        assert!(wrong.is_revealed(gate));
+        assert!(!terrain(&wrong).is_hit(gate));
+    }
+}
@@ -1,7 +1,19 @@
+pub mod achievements;
 pub mod delta_time;
+pub mod drips;
 pub mod entity;
+pub mod flow;
+pub mod ghost;
 pub mod hsv2rgb;
+pub mod input;
 pub mod level;
 pub mod main_loop;
+pub mod paint;
 pub mod rendering;
+pub mod replay;
+pub mod save;
+pub mod score;
+pub mod speedrun;
+pub mod stats;
+pub mod ui;
 pub mod wasm;
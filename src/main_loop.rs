@@ -1,9 +1,18 @@
 use crate::delta_time::DeltaTimer;
-use crate::entity::game::Game;
+use crate::flow::{FlowCommand, GameFlow, MenuAction};
+use crate::input::KeyboardInput;
 use crate::level::Level;
+use crate::rendering::camera::{Camera, step_zoom};
 use crate::rendering::game_renderer::{GameRenderer, RenderConfig};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::rendering::offscreen::save_screenshot;
+use crate::rendering::post;
+use crate::replay::Replay;
+use crate::save::platform_store;
+use crate::ui::screen_overlay;
 use anyhow::Context;
-use winit::event::{Event, WindowEvent};
+use glam::uvec2;
+use winit::event::{ElementState, Event, KeyEvent, WindowEvent};
 use winit::event_loop::EventLoop;
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::Window;
@@ -49,16 +58,18 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> anyhow::Result<()
         device: device.clone(),
         queue,
         swapchain_format: surface.get_capabilities(&adapter).formats[0],
+        scene_format: post::scene_format(&adapter),
     })?;
+    renderer.camera = Camera::new(uvec2(config.width, config.height));
 
-    let levels = Level::load_file_tree()?;
-    let mut current_level_idx = 0;
-
-    // oof duplicated
-    let mut game = Game::new(levels[current_level_idx].clone());
-    renderer.level.load_level(game.level.clone());
+    let mut flow = GameFlow::new(Level::load_file_tree()?, platform_store());
+    renderer.ui.load_thumbnails(&flow.levels);
+    renderer
+        .level
+        .load_level(flow.game.level.clone(), flow.store.as_ref());
 
     let mut delta_timer = DeltaTimer::default();
+    let mut keyboard = KeyboardInput::default();
     #[expect(deprecated)]
     event_loop.run(|event, target| match event {
         Event::WindowEvent {
@@ -66,12 +77,31 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> anyhow::Result<()
             window_id,
         } if window_id == window.id() => match event {
             WindowEvent::KeyboardInput { event, .. } => {
-                game.player.handle_key_event(event);
-                match event.physical_key {
-                    PhysicalKey::Code(KeyCode::Escape) => {
-                        target.exit();
+                keyboard.handle_key_event(event);
+                if let Err(err) = screenshot_hotkey(&renderer, &flow, event) {
+                    eprintln!("screenshot failed: {err:#}");
+                }
+                let command = if let Some(action) = menu_action(event) {
+                    flow.handle_action(action)
+                } else if let Some(keep_paint) = restart_action(event) {
+                    flow.restart_level(keep_paint)
+                } else if toggle_setting(&mut flow, event) {
+                    None
+                } else {
+                    replay_hotkeys(&mut flow, event).unwrap_or_else(|err| {
+                        eprintln!("replay failed: {err:#}");
+                        None
+                    })
+                };
+                match command {
+                    Some(FlowCommand::LoadLevel) => renderer
+                        .level
+                        .load_level(flow.game.level.clone(), flow.store.as_ref()),
+                    Some(FlowCommand::RestartLevel) => {
+                        renderer.level.reload_ghost(flow.store.as_ref())
                     }
-                    _ => (),
+                    Some(FlowCommand::Exit) => target.exit(),
+                    None => (),
                 }
             }
             WindowEvent::Resized(new_size) => {
@@ -79,16 +109,26 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> anyhow::Result<()
                 config.width = new_size.width.max(1);
                 config.height = new_size.height.max(1);
                 surface.configure(&device, &config);
+                renderer.camera.surface_size = uvec2(config.width, config.height);
                 // On macos the window needs to be redrawn manually after resizing
                 window.request_redraw();
             }
             WindowEvent::RedrawRequested => {
                 // UPDATE
                 let dt = delta_timer.next();
-                let despawned_particles = game.update(dt);
+                let outcome = flow.update(dt, keyboard.tick_input());
+
+                renderer.camera.zoom = flow.save.settings.zoom;
+                renderer.camera.scaling = flow.save.settings.scaling;
+                renderer
+                    .follow
+                    .update(*dt, &renderer.camera, &flow.game, &outcome.events);
 
                 // BUBBLE DRAW
-                renderer.level.draw_color_splashes(&despawned_particles);
+                renderer.begin_frame();
+                renderer
+                    .level
+                    .draw_color_splashes(&outcome.stamps, &outcome.strokes);
 
                 // MAIN DRAW
                 let frame = surface
@@ -97,22 +137,157 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> anyhow::Result<()
                 let view = frame
                     .texture
                     .create_view(&wgpu::TextureViewDescriptor::default());
-                renderer.draw(&game, view);
+                renderer.show_ghost = flow.save.settings.show_ghost;
+                renderer.show_outlines = flow.save.settings.assists.outlines;
+                renderer.post_settings = flow.save.settings.post;
+                renderer.time = dt.since_start;
+                renderer.draw(&flow.game, &screen_overlay(&flow), view);
                 frame.present();
                 window.request_redraw();
-
-                // LEVEL LOAD
-                // oof duplicated
-                if game.portal.jump_to_next_level() {
-                    current_level_idx += 1;
-                    game = Game::new(levels[current_level_idx].clone());
-                    renderer.level.load_level(game.level.clone());
-                }
             }
-            WindowEvent::CloseRequested => target.exit(),
+            WindowEvent::CloseRequested => {
+                flow.save_progress();
+                target.exit()
+            }
             _ => {}
         },
         _ => {}
     })?;
     Ok(())
 }
+
+fn menu_action(event: &KeyEvent) -> Option<MenuAction> {
+    if event.state != ElementState::Pressed || event.repeat {
+        return None;
+    }
+    match event.physical_key {
+        PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) => Some(MenuAction::Confirm),
+        PhysicalKey::Code(KeyCode::Escape) => Some(MenuAction::Back),
+        PhysicalKey::Code(KeyCode::Tab) => Some(MenuAction::Stats),
+        PhysicalKey::Code(KeyCode::KeyL) => Some(MenuAction::LevelSelect),
+        PhysicalKey::Code(KeyCode::KeyO) => Some(MenuAction::Assists),
+        PhysicalKey::Code(KeyCode::ArrowLeft | KeyCode::KeyA) => {
+            Some(MenuAction::Move { dx: -1, dy: 0 })
+        }
+        PhysicalKey::Code(KeyCode::ArrowRight | KeyCode::KeyD) => {
+            Some(MenuAction::Move { dx: 1, dy: 0 })
+        }
+        PhysicalKey::Code(KeyCode::ArrowUp | KeyCode::KeyW) => {
+            Some(MenuAction::Move { dx: 0, dy: -1 })
+        }
+        PhysicalKey::Code(KeyCode::ArrowDown | KeyCode::KeyS) => {
+            Some(MenuAction::Move { dx: 0, dy: 1 })
+        }
+        _ => None,
+    }
+}
+
+/// R restarts the level keeping the paint, Backspace restarts it from scratch, returning whether to
+/// keep the paint
+///
+/// Not Shift+R, as Shift also creates bubbles.
+fn restart_action(event: &KeyEvent) -> Option<bool> {
+    if event.state != ElementState::Pressed || event.repeat {
+        return None;
+    }
+    match event.physical_key {
+        PhysicalKey::Code(KeyCode::KeyR) => Some(true),
+        PhysicalKey::Code(KeyCode::Backspace) => Some(false),
+        _ => None,
+    }
+}
+
+/// G toggles the ghost, T toggles the timer, +/- zoom, I toggles integer scaling and B, V and C toggle
+/// bloom, the vignette and color grading
+fn toggle_setting(flow: &mut GameFlow, event: &KeyEvent) -> bool {
+    if event.state != ElementState::Pressed || event.repeat {
+        return false;
+    }
+    let settings = &mut flow.save.settings;
+    match event.physical_key {
+        PhysicalKey::Code(KeyCode::KeyG) => settings.show_ghost = !settings.show_ghost,
+        PhysicalKey::Code(KeyCode::KeyT) => settings.show_timer = !settings.show_timer,
+        PhysicalKey::Code(KeyCode::Equal | KeyCode::NumpadAdd) => {
+            settings.zoom = step_zoom(settings.zoom, 1)
+        }
+        PhysicalKey::Code(KeyCode::Minus | KeyCode::NumpadSubtract) => {
+            settings.zoom = step_zoom(settings.zoom, -1)
+        }
+        PhysicalKey::Code(KeyCode::KeyI) => settings.scaling = settings.scaling.toggle(),
+        PhysicalKey::Code(KeyCode::KeyB) => settings.post.bloom = !settings.post.bloom,
+        PhysicalKey::Code(KeyCode::KeyV) => settings.post.vignette = !settings.post.vignette,
+        PhysicalKey::Code(KeyCode::KeyC) => {
+            settings.post.color_grading = !settings.post.color_grading
+        }
+        _ => return false,
+    }
+    flow.save_progress();
+    true
+}
+
+/// F5 saves the inputs of the current level attempt, F9 plays back the saved replay of the current level,
+/// or its shipped solution if none was saved
+fn replay_hotkeys(flow: &mut GameFlow, event: &KeyEvent) -> anyhow::Result<Option<FlowCommand>> {
+    if event.state != ElementState::Pressed || event.repeat {
+        return Ok(None);
+    }
+    match event.physical_key {
+        PhysicalKey::Code(KeyCode::F5) => {
+            flow.recording.store(flow.store.as_mut())?;
+            Ok(None)
+        }
+        PhysicalKey::Code(KeyCode::F9) => {
+            let replay = match Replay::load_stored(flow.store.as_ref(), flow.level_idx)? {
+                Some(replay) => replay,
+                None => shipped_solution(flow.level_idx)?,
+            };
+            Ok(Some(flow.start_replay(replay)?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// F12 saves a screenshot of the current frame
+#[cfg(not(target_arch = "wasm32"))]
+fn screenshot_hotkey(
+    renderer: &GameRenderer,
+    flow: &GameFlow,
+    event: &KeyEvent,
+) -> anyhow::Result<()> {
+    if event.state != ElementState::Pressed
+        || event.repeat
+        || event.physical_key != PhysicalKey::Code(KeyCode::F12)
+    {
+        return Ok(());
+    }
+    let image = renderer.render_image(
+        &flow.game,
+        &screen_overlay(flow),
+        renderer.camera.surface_size,
+    )?;
+    let path = save_screenshot(&image)?;
+    eprintln!("saved screenshot to {}", path.display());
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn screenshot_hotkey(
+    _renderer: &GameRenderer,
+    _flow: &GameFlow,
+    _event: &KeyEvent,
+) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn shipped_solution(level_idx: usize) -> anyhow::Result<Replay> {
+    Replay::load(
+        std::path::Path::new(crate::replay::SOLUTIONS_DIR).join(Replay::file_name(level_idx)),
+    )
+}
+
+/// the web build doesn't ship the solutions, only replays saved with F5 can be played back
+#[cfg(target_arch = "wasm32")]
+fn shipped_solution(_level_idx: usize) -> anyhow::Result<Replay> {
+    anyhow::bail!("no replay saved for this level")
+}
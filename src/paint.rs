@@ -0,0 +1,325 @@
+use crate::drips::DripStroke;
+use crate::entity::splash::Splash;
+use crate::hsv2rgb::rgb2hue;
+use crate::level::Level;
+use glam::{IVec2, Mat2, UVec2, Vec2, Vec4, vec2};
+use image::{GrayImage, ImageFormat, ImageReader, Luma, RgbaImage};
+use rand::distr::Open01;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::Cursor;
+use std::sync::OnceLock;
+
+pub static SPLAT_TEXTURES: [&[u8]; 3] = [
+    include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/colorsplash/1.png"
+    ))
+    .as_slice(),
+    include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/colorsplash/2.png"
+    ))
+    .as_slice(),
+    include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/colorsplash/3.png"
+    ))
+    .as_slice(),
+];
+
+/// the same thresholds `fs_masked` discards fragments with, in `0..=255`
+const ALPHA_THRESHOLD: f32 = 0.01 * 255.;
+const MASK_THRESHOLD: u8 = 3;
+const UNPAINTED: Luma<u8> = Luma([0]);
+
+pub fn load_splat_images() -> anyhow::Result<Vec<RgbaImage>> {
+    SPLAT_TEXTURES
+        .iter()
+        .map(|bytes| {
+            Ok(
+                ImageReader::with_format(Cursor::new(*bytes), ImageFormat::Png)
+                    .decode()?
+                    .flipv()
+                    .into_rgba8(),
+            )
+        })
+        .collect()
+}
+
+/// alpha channels of [`SPLAT_TEXTURES`]
+fn splat_shapes() -> &'static [GrayImage] {
+    static SHAPES: OnceLock<Vec<GrayImage>> = OnceLock::new();
+    SHAPES.get_or_init(|| {
+        load_splat_images()
+            .expect("embedded splat textures are valid")
+            .iter()
+            .map(|image| {
+                GrayImage::from_fn(image.width(), image.height(), |x, y| {
+                    Luma([image.get_pixel(x, y).0[3]])
+                })
+            })
+            .collect()
+    })
+}
+
+/// A single splat of paint onto the level, shared by the CPU [`RevealMask`] and the GPU paint
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SplatStamp {
+    pub pos: Vec2,
+    /// half the edge length of the splat quad
+    pub size: f32,
+    pub rotation: f32,
+    /// index into [`SPLAT_TEXTURES`]
+    pub texture: usize,
+    pub color: Vec4,
+}
+
+impl SplatStamp {
+    pub fn random(splash: &Splash, rng: &mut impl Rng) -> Self {
+        Self {
+            pos: splash.pos,
+            size: 20. + 10. * rng.sample::<f32, _>(Open01),
+            texture: rng.random_range(0..SPLAT_TEXTURES.len()),
+            color: splash.color,
+            rotation: rng.sample(Open01),
+        }
+    }
+
+    /// The four corners of the splat quad in Z order, vertically flipped like the level texture pass expects
+    pub fn corners(&self, level_height: u32) -> [Vec2; 4] {
+        let pos = vec2(self.pos.x, level_height as f32 - self.pos.y);
+        let rot = Mat2::from_angle(self.rotation);
+        [vec2(-1., -1.), vec2(-1., 1.), vec2(1., -1.), vec2(1., 1.)]
+            .map(|corner| rot * corner * self.size + pos)
+    }
+}
+
+/// Samples `image` at `uv` like a linear filtering sampler clamping to the edge does
+fn sample_linear(image: &GrayImage, uv: Vec2) -> f32 {
+    let max = UVec2::new(image.width(), image.height()) - 1;
+    let texel = uv * UVec2::new(image.width(), image.height()).as_vec2() - 0.5;
+    let min = texel.floor();
+    let t = texel - min;
+    let texel = |offset: IVec2| {
+        let pos = (min.as_ivec2() + offset).clamp(IVec2::ZERO, max.as_ivec2());
+        image.get_pixel(pos.x as u32, pos.y as u32).0[0] as f32
+    };
+    let bottom = texel(IVec2::ZERO) * (1. - t.x) + texel(IVec2::X) * t.x;
+    let top = texel(IVec2::Y) * (1. - t.x) + texel(IVec2::ONE) * t.x;
+    bottom * (1. - t.y) + top * t.y
+}
+
+/// Which pixels of a level have been revealed by paint and the hue they were painted last, mirroring
+/// the GPU level texture
+#[derive(Debug, Clone)]
+pub struct RevealMask {
+    /// `0` for unpainted pixels, otherwise the painted hue encoded by [`encode_hue`]
+    pub painted: GrayImage,
+    revealed: usize,
+    /// pixels that can be painted at all, see [`Level::collision_pixels`]
+    paintable: usize,
+    rng: StdRng,
+}
+
+fn encode_hue(hue: f32) -> Luma<u8> {
+    Luma([1 + (hue.rem_euclid(1.) * 254.).round() as u8])
+}
+
+fn decode_hue(value: Luma<u8>) -> f32 {
+    (value.0[0] - 1) as f32 / 254.
+}
+
+impl RevealMask {
+    /// the stamp randomness is seeded by the level, so replays paint the same
+    pub fn new(level: &Level) -> Self {
+        Self {
+            painted: GrayImage::new(level.size.x, level.size.y),
+            revealed: 0,
+            paintable: level.collision_pixels(),
+            rng: StdRng::seed_from_u64(level.hash),
+        }
+    }
+
+    pub fn is_revealed(&self, pos: IVec2) -> bool {
+        self.painted_hue(pos).is_some()
+    }
+
+    /// the hue the pixel was painted with last, if it was painted at all
+    pub fn painted_hue(&self, pos: IVec2) -> Option<f32> {
+        if pos.cmplt(IVec2::ZERO).any() {
+            return None;
+        }
+        self.painted
+            .get_pixel_checked(pos.x as u32, pos.y as u32)
+            .filter(|p| **p != UNPAINTED)
+            .map(|p| decode_hue(*p))
+    }
+
+    pub fn revealed_count(&self) -> usize {
+        self.revealed
+    }
+
+    /// the revealed fraction of the paintable pixels, in `0..=1`
+    pub fn coverage(&self) -> f32 {
+        if self.paintable == 0 {
+            1.
+        } else {
+            self.revealed as f32 / self.paintable as f32
+        }
+    }
+
+    /// Turns despawned splashes into stamps and applies them
+    pub fn paint(&mut self, level: &Level, splashes: &[Splash]) -> Vec<SplatStamp> {
+        splashes
+            .iter()
+            .map(|splash| {
+                let stamp = SplatStamp::random(splash, &mut self.rng);
+                self.stamp(level, &stamp);
+                stamp
+            })
+            .collect()
+    }
+
+    /// Rasterizes the stamp like the `fs_masked` pipeline does, sampling the splat shape bilinearly
+    pub fn stamp(&mut self, level: &Level, stamp: &SplatStamp) {
+        if level.size.cmpeq(UVec2::ZERO).any() {
+            return;
+        }
+        let shape = &splat_shapes()[stamp.texture];
+        let hue = encode_hue(rgb2hue(stamp.color.truncate()));
+        let inv_rot = Mat2::from_angle(-stamp.rotation);
+        let extent = Vec2::splat(stamp.size * std::f32::consts::SQRT_2);
+        let min = (stamp.pos - extent).floor().max(Vec2::ZERO).as_uvec2();
+        let max = (stamp.pos + extent).ceil().as_uvec2().min(level.size - 1);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                if level.collision_map.get_pixel(x, y).0[0] < MASK_THRESHOLD {
+                    continue;
+                }
+                // the level texture is y-down, mirror back into it before undoing the rotation
+                let center = vec2(x as f32, y as f32) + 0.5;
+                let offset = (center - stamp.pos) * vec2(1., -1.);
+                let local = inv_rot * offset / stamp.size;
+                if local.abs().cmpgt(Vec2::ONE).any() {
+                    continue;
+                }
+                let uv = (local + 1.) * 0.5;
+                if sample_linear(shape, uv) >= ALPHA_THRESHOLD {
+                    self.paint_pixel(x, y, hue);
+                }
+            }
+        }
+    }
+
+    /// Rasterizes the drip stroke like the GPU does, covering the pixels whose center it contains
+    pub fn stroke(&mut self, level: &Level, stroke: &DripStroke) {
+        let hue = encode_hue(rgb2hue(stroke.color.truncate()));
+        let min = (stroke.min - 0.5).ceil().max(Vec2::ZERO).as_uvec2();
+        let max = (stroke.max - 0.5)
+            .ceil()
+            .max(Vec2::ZERO)
+            .as_uvec2()
+            .min(level.size);
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                if level.collision_map.get_pixel(x, y).0[0] >= MASK_THRESHOLD {
+                    self.paint_pixel(x, y, hue);
+                }
+            }
+        }
+    }
+
+    fn paint_pixel(&mut self, x: u32, y: u32, hue: Luma<u8>) {
+        let pixel = self.painted.get_pixel_mut(x, y);
+        if *pixel == UNPAINTED {
+            self.revealed += 1;
+        }
+        *pixel = hue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::player::Player;
+    use crate::level::LevelMeta;
+    use glam::ivec2;
+
+    fn stamp(pos: Vec2) -> SplatStamp {
+        SplatStamp {
+            pos,
+            size: 6.,
+            rotation: 0.,
+            texture: 0,
+            color: Player::hue_color(0.25),
+        }
+    }
+
+    #[test]
+    fn stamps_paint_within_their_quad() {
+        // solid but for the empty block in the top right
+        let level = Level::from_ascii(&["### ", "####", "####", "####"]);
+        let mut paint = RevealMask::new(&level);
+        paint.stamp(&level, &stamp(vec2(16., 16.)));
+        assert!(paint.revealed_count() > 0);
+        assert!((paint.painted_hue(ivec2(16, 16)).unwrap() - 0.25).abs() < 0.01);
+        for (x, y, _) in paint
+            .painted
+            .enumerate_pixels()
+            .filter(|(_, _, p)| **p != UNPAINTED)
+        {
+            assert!((10..22).contains(&x) && (10..22).contains(&y), "({x}, {y})");
+        }
+
+        let mut corner = RevealMask::new(&level);
+        corner.stamp(&level, &stamp(vec2(24., 24.)));
+        assert!(corner.is_revealed(ivec2(23, 23)));
+        assert!(!corner.is_revealed(ivec2(24, 24)));
+    }
+
+    #[test]
+    fn stamps_clip_at_the_level_edges() {
+        let level = Level::from_ascii(&["####", "####", "####", "####"]);
+        let mut paint = RevealMask::new(&level);
+        paint.stamp(&level, &stamp(vec2(0., 0.)));
+        assert!(paint.is_revealed(ivec2(0, 0)));
+        let corner = paint.revealed_count();
+        paint.stamp(&level, &stamp(vec2(32., 32.)));
+        assert!(paint.is_revealed(ivec2(31, 31)));
+        assert!(paint.revealed_count() > corner);
+        paint.stamp(&level, &stamp(vec2(-100., 100.)));
+        assert_eq!(
+            paint.revealed_count(),
+            paint.painted.pixels().filter(|p| **p != UNPAINTED).count()
+        );
+
+        let empty = Level::from_image(RgbaImage::new(0, 0), LevelMeta::default());
+        let mut paint = RevealMask::new(&empty);
+        paint.stamp(&empty, &stamp(vec2(0., 0.)));
+        assert_eq!(paint.revealed_count(), 0);
+    }
+
+    #[test]
+    fn same_level_paints_the_same() {
+        let level = Level::from_ascii(&["####", "####", "####", "####"]);
+        let splashes: Vec<_> = (0..8)
+            .map(|i| Splash {
+                pos: vec2(4. * i as f32, 16.),
+                vel: Vec2::ZERO,
+                color: Player::hue_color(i as f32 / 8.),
+                age: 0,
+            })
+            .collect();
+        let paint = |level: &Level| {
+            let mut paint = RevealMask::new(level);
+            let stamps = paint.paint(level, &splashes);
+            (stamps, paint.painted)
+        };
+        let (stamps, painted) = paint(&level);
+        assert_eq!(paint(&level), (stamps.clone(), painted));
+
+        let other = Level::from_ascii(&["####", "####", "####", "### "]);
+        assert_ne!(paint(&other).0, stamps);
+    }
+}
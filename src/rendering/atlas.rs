@@ -0,0 +1,259 @@
+use crate::level::fnv1a;
+use crate::paint::SPLAT_TEXTURES;
+use crate::rendering::game_renderer::RenderConfig;
+use crate::rendering::quad_texture::{QuadTexture, QuadTextureBindGroupLayout};
+use glam::{UVec2, Vec2, uvec2, vec2};
+use image::RgbaImage;
+
+pub static BUBBLE_TEXTURE: &[u8] =
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/bubble.png")).as_slice();
+pub static SPLASH_TEXTURE: &[u8] =
+    include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/Splash.png")).as_slice();
+
+/// The pngs packed into the [`Atlas`] of every [`crate::rendering::quad::QuadRenderer`]
+pub static ATLAS_TEXTURES: [&[u8]; 5] = [
+    BUBBLE_TEXTURE,
+    SPLASH_TEXTURE,
+    SPLAT_TEXTURES[0],
+    SPLAT_TEXTURES[1],
+    SPLAT_TEXTURES[2],
+];
+
+/// texels around every image, filled with its edge so linear filtering does not bleed into neighbours
+pub const ATLAS_PADDING: u32 = 2;
+
+/// A sub-rect of a texture, in UV coordinates
+#[derive(Debug, Clone)]
+pub struct TextureRegion {
+    pub texture: QuadTexture,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+}
+
+impl TextureRegion {
+    /// the whole `texture`
+    pub fn full(texture: QuadTexture) -> Self {
+        Self {
+            texture,
+            uv_min: Vec2::ZERO,
+            uv_max: Vec2::ONE,
+        }
+    }
+
+    /// Maps a tex coord in `0..=1` to the region
+    pub fn uv(&self, tex_coord: Vec2) -> Vec2 {
+        self.uv_min + tex_coord * (self.uv_max - self.uv_min)
+    }
+
+    /// the same region, sampled upside down
+    pub fn flip_y(mut self) -> Self {
+        std::mem::swap(&mut self.uv_min.y, &mut self.uv_max.y);
+        self
+    }
+}
+
+/// Packs images into rows of a power of two sized image, tallest first
+///
+/// Returns the size of the atlas and the position of every image within it, excluding the padding.
+pub fn pack(sizes: &[UVec2], padding: u32) -> (UVec2, Vec<UVec2>) {
+    let padded = |size: UVec2| size + 2 * padding;
+    let area = sizes
+        .iter()
+        .map(|s| padded(*s).element_product())
+        .sum::<u32>();
+    let widest = sizes.iter().map(|s| padded(*s).x).max().unwrap_or(1);
+    let width = widest.max(area.isqrt()).next_power_of_two();
+
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| std::cmp::Reverse(sizes[*i].y));
+    let mut positions = vec![UVec2::ZERO; sizes.len()];
+    let mut cursor = UVec2::ZERO;
+    let mut row_height = 0;
+    for i in order {
+        let size = padded(sizes[i]);
+        if cursor.x + size.x > width {
+            cursor = uvec2(0, cursor.y + row_height);
+            row_height = 0;
+        }
+        positions[i] = cursor + padding;
+        cursor.x += size.x;
+        row_height = row_height.max(size.y);
+    }
+    let height = (cursor.y + row_height).max(1).next_power_of_two();
+    (uvec2(width, height), positions)
+}
+
+/// Copies `images` into one image, see [`pack`]
+pub fn pack_images(images: &[RgbaImage]) -> (RgbaImage, Vec<UVec2>) {
+    let sizes = images
+        .iter()
+        .map(|image| uvec2(image.width(), image.height()))
+        .collect::<Vec<_>>();
+    let (size, positions) = pack(&sizes, ATLAS_PADDING);
+    let mut atlas = RgbaImage::new(size.x, size.y);
+    for (image, pos) in images.iter().zip(&positions) {
+        // clamp to the edge of the image within the padding
+        let max = uvec2(image.width(), image.height()) - 1;
+        let padded = UVec2::splat(2 * ATLAS_PADDING) + max + 1;
+        for y in 0..padded.y {
+            for x in 0..padded.x {
+                let src = (uvec2(x, y).saturating_sub(UVec2::splat(ATLAS_PADDING))).min(max);
+                atlas.put_pixel(
+                    pos.x - ATLAS_PADDING + x,
+                    pos.y - ATLAS_PADDING + y,
+                    *image.get_pixel(src.x, src.y),
+                );
+            }
+        }
+    }
+    (atlas, positions)
+}
+
+/// One texture holding many images, so they can be drawn with the same bind group
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    /// the [`source_id`] of every packed png
+    ids: Vec<u64>,
+    regions: Vec<TextureRegion>,
+}
+
+/// identifies a png by its contents, so every copy of it finds the same region
+fn source_id(bytes: &[u8]) -> u64 {
+    fnv1a(bytes)
+}
+
+/// the index of the png `bytes` among the pngs with the given ids
+fn find_source(ids: &[u64], bytes: &[u8]) -> Option<usize> {
+    let id = source_id(bytes);
+    ids.iter().position(|source| *source == id)
+}
+
+impl Atlas {
+    pub fn new(
+        config: &RenderConfig,
+        layout: &QuadTextureBindGroupLayout,
+        sources: &[&[u8]],
+    ) -> anyhow::Result<Self> {
+        let images = sources
+            .iter()
+            .map(|bytes| Ok(image::load_from_memory(bytes)?.into_rgba8()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let (image, positions) = pack_images(&images);
+        let texture = QuadTexture::upload(config, layout, &image);
+        let atlas_size = vec2(image.width() as f32, image.height() as f32);
+        let regions = images
+            .iter()
+            .zip(positions)
+            .map(|(image, pos)| TextureRegion {
+                texture: texture.clone(),
+                uv_min: pos.as_vec2() / atlas_size,
+                uv_max: (pos + uvec2(image.width(), image.height())).as_vec2() / atlas_size,
+            })
+            .collect();
+        Ok(Self {
+            ids: sources.iter().map(|bytes| source_id(bytes)).collect(),
+            regions,
+        })
+    }
+
+    /// the region the png `bytes` were packed into, wherever the bytes live
+    pub fn get(&self, bytes: &[u8]) -> Option<&TextureRegion> {
+        find_source(&self.ids, bytes).map(|i| &self.regions[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_math::URect;
+
+    fn padded_rects(sizes: &[UVec2], positions: &[UVec2], padding: u32) -> Vec<URect> {
+        sizes
+            .iter()
+            .zip(positions)
+            .map(|(size, pos)| URect::from_corners(*pos - padding, *pos + *size + padding))
+            .collect()
+    }
+
+    #[test]
+    fn packed_images_do_not_overlap() {
+        let sizes = [
+            uvec2(64, 64),
+            uvec2(10, 30),
+            uvec2(100, 5),
+            uvec2(1, 1),
+            uvec2(33, 64),
+            uvec2(20, 20),
+        ];
+        let (size, positions) = pack(&sizes, ATLAS_PADDING);
+        assert!(size.x.is_power_of_two() && size.y.is_power_of_two());
+        let rects = padded_rects(&sizes, &positions, ATLAS_PADDING);
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.max.cmple(size).all());
+            for b in &rects[i + 1..] {
+                assert!(a.intersect(*b).is_empty(), "{a:?} overlaps {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn padding_surrounds_every_image() {
+        let sizes = [uvec2(4, 4), uvec2(4, 4)];
+        let (size, positions) = pack(&sizes, 3);
+        // the padded images are 10 wide, two of them don't fit a row of 16
+        assert_eq!(positions, [uvec2(3, 3), uvec2(3, 13)]);
+        assert_eq!(size, uvec2(16, 32));
+    }
+
+    #[test]
+    fn padding_clamps_to_the_image_edge() {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, image::Rgba([0, 255, 0, 255]));
+        let (atlas, positions) = pack_images(&[image.clone()]);
+        let pos = positions[0];
+        assert_eq!(
+            atlas.get_pixel(pos.x - ATLAS_PADDING, pos.y - ATLAS_PADDING),
+            image.get_pixel(0, 0)
+        );
+        assert_eq!(
+            atlas.get_pixel(pos.x + 1 + ATLAS_PADDING, pos.y + ATLAS_PADDING),
+            image.get_pixel(1, 0)
+        );
+    }
+
+    #[test]
+    fn shipped_textures_fit() {
+        let images = ATLAS_TEXTURES
+            .iter()
+            .map(|bytes| image::load_from_memory(bytes).unwrap().into_rgba8())
+            .collect::<Vec<_>>();
+        let (atlas, positions) = pack_images(&images);
+        assert!(atlas.width().is_power_of_two() && atlas.height().is_power_of_two());
+        let sizes = images
+            .iter()
+            .map(|image| uvec2(image.width(), image.height()))
+            .collect::<Vec<_>>();
+        let rects = padded_rects(&sizes, &positions, ATLAS_PADDING);
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[i + 1..] {
+                assert!(a.intersect(*b).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn sources_are_found_through_copies() {
+        let ids = ATLAS_TEXTURES
+            .iter()
+            .map(|bytes| source_id(bytes))
+            .collect::<Vec<_>>();
+        for (i, bytes) in ATLAS_TEXTURES.iter().enumerate() {
+            assert_eq!(find_source(&ids, bytes), Some(i));
+            let copy = bytes.to_vec();
+            assert!(!std::ptr::eq(copy.as_slice(), *bytes));
+            assert_eq!(find_source(&ids, &copy), Some(i));
+        }
+        assert_eq!(find_source(&ids, &BUBBLE_TEXTURE[1..]), None);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::level::BackgroundLayer;
+use crate::rendering::framedata::FrameDataBinding;
+use crate::rendering::quad::{QuadRenderer, QuadVertex, QuadVertexBuffer};
+use crate::rendering::quad_texture::{QuadTexture, QuadTextureBindGroupLayout};
+use glam::{Vec2, Vec4, vec2};
+use wgpu::{AddressMode, FilterMode, RenderPass, SamplerDescriptor};
+
+/// The tiling textures [`BackgroundLayer::texture`] may name, usually white so they can be tinted
+pub const BACKGROUND_TEXTURES: [(&str, &[u8]); 2] = [
+    (
+        "clouds",
+        include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/backgrounds/clouds.png"
+        ))
+        .as_slice(),
+    ),
+    (
+        "stars",
+        include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/backgrounds/stars.png"
+        ))
+        .as_slice(),
+    ),
+];
+
+pub struct BackgroundRenderer {
+    pub quad: QuadRenderer,
+    /// uploaded [`BACKGROUND_TEXTURES`] with their size, sampled with wrapping
+    textures: Vec<(&'static str, QuadTexture, Vec2)>,
+}
+
+impl BackgroundRenderer {
+    pub fn new(quad: QuadRenderer) -> anyhow::Result<Self> {
+        let layout = QuadTextureBindGroupLayout {
+            sampler: quad.config.device.create_sampler(&SamplerDescriptor {
+                label: Some("Background sampler"),
+                address_mode_u: AddressMode::Repeat,
+                address_mode_v: AddressMode::Repeat,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Nearest,
+                ..Default::default()
+            }),
+            ..quad.texture_layout.clone()
+        };
+        let textures = BACKGROUND_TEXTURES
+            .iter()
+            .map(|(name, bytes)| {
+                let image = image::load_from_memory(bytes)?.into_rgba8();
+                let size = vec2(image.width() as f32, image.height() as f32);
+                Ok((
+                    *name,
+                    QuadTexture::upload(&quad.config, &layout, &image),
+                    size,
+                ))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { quad, textures })
+    }
+
+    /// Covers the visible area centered on `center` with the `layers`, back to front
+    ///
+    /// Layers with unknown textures are skipped.
+    pub fn draw(
+        &self,
+        rpass: &mut RenderPass,
+        frame_data: &FrameDataBinding,
+        layers: &[BackgroundLayer],
+        center: Vec2,
+        visible_size: Vec2,
+        time: f32,
+    ) {
+        for layer in layers {
+            let Some((_, texture, size)) = self
+                .textures
+                .iter()
+                .find(|(name, _, _)| *name == layer.texture)
+            else {
+                continue;
+            };
+            // where the layer's origin is in the world, wrapped to keep the tex coords small
+            let origin = (center * (1. - Vec2::from(layer.scroll))
+                + Vec2::from(layer.auto_scroll) * time)
+                .rem_euclid(*size);
+            let vtx_color = Vec4::from(layer.tint);
+            let vertices =
+                [vec2(-1., -1.), vec2(-1., 1.), vec2(1., -1.), vec2(1., 1.)].map(|corner| {
+                    let position = center + corner * visible_size * 0.5;
+                    // images are stored top row first, the world is y-up
+                    let tex_coord = (position - origin) / *size * vec2(1., -1.);
+                    QuadVertex {
+                        position,
+                        tex_coord,
+                        vtx_color,
+                    }
+                });
+            self.quad.draw_texture(
+                rpass,
+                frame_data,
+                &QuadVertexBuffer::stream(&self.quad.vertex_stream, &vertices),
+                texture,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+
+    #[test]
+    fn level_backgrounds_exist() {
+        for level in Level::load_file_tree().unwrap() {
+            for layer in &level.meta.background {
+                assert!(
+                    BACKGROUND_TEXTURES
+                        .iter()
+                        .any(|(name, _)| *name == layer.texture),
+                    "unknown background texture {:?}",
+                    layer.texture
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use crate::entity::game::{Game, GameEvent};
+use crate::rendering::framedata::VIEWPORT_SIZE;
+use crate::save::Scaling;
+use glam::{UVec2, Vec2, Vec4, uvec2, vec2};
+use wgpu::RenderPass;
+
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 2.;
+/// factor a single zoom step changes the zoom by
+pub const ZOOM_STEP: f32 = 1.25;
+
+/// Maps the world and the UI onto a surface of any size, keeping the aspect ratio of
+/// [`VIEWPORT_SIZE`] by adding black bars
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    pub surface_size: UVec2,
+    pub scaling: Scaling,
+    /// `2.` shows half as much of the world, does not affect the UI
+    pub zoom: f32,
+}
+
+impl Camera {
+    pub fn new(surface_size: UVec2) -> Self {
+        Self {
+            surface_size,
+            scaling: Scaling::default(),
+            zoom: 1.,
+        }
+    }
+
+    /// surface pixels per [`VIEWPORT_SIZE`] unit
+    pub fn scale(&self) -> f32 {
+        let fit = (self.surface_size.as_vec2() / VIEWPORT_SIZE).min_element();
+        match self.scaling {
+            // surfaces smaller than the viewport can't scale by a whole multiple
+            Scaling::Integer if fit >= 1. => fit.floor(),
+            _ => fit,
+        }
+    }
+
+    /// position and size of the area rendered to, in surface pixels
+    pub fn letterbox(&self) -> (UVec2, UVec2) {
+        let size = (VIEWPORT_SIZE * self.scale())
+            .round()
+            .as_uvec2()
+            .clamp(UVec2::ONE, self.surface_size.max(UVec2::ONE));
+        let pos = (self.surface_size.saturating_sub(size)) / 2;
+        (pos, size)
+    }
+
+    /// Restricts drawing to [`Self::letterbox`], the bars keep the clear color
+    pub fn apply(&self, rpass: &mut RenderPass) {
+        let (pos, size) = self.letterbox();
+        let (pos, size) = (pos.as_vec2(), size.as_vec2());
+        rpass.set_viewport(pos.x, pos.y, size.x, size.y, 0., 1.);
+    }
+
+    /// the size of the world area that is visible
+    pub fn visible_size(&self) -> Vec2 {
+        VIEWPORT_SIZE / self.zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+    }
+
+    /// Moves `focus` so the visible area stays within the level, or centers the level if it is
+    /// smaller than the visible area
+    pub fn clamp(&self, level_size: UVec2, focus: Vec2) -> Vec2 {
+        let visible = self.visible_size();
+        let half = visible * 0.5;
+        let level = level_size.as_vec2();
+        Vec2::select(
+            level.cmplt(visible),
+            level * 0.5,
+            focus.clamp(half, (level - half).max(half)),
+        )
+    }
+
+    /// viewport centered on `focus`, clamped to the level, see [`Self::clamp`]
+    pub fn viewport(&self, level_size: UVec2, focus: Vec2) -> Vec4 {
+        self.viewport_at(self.clamp(level_size, focus))
+    }
+
+    /// viewport centered on `center`, even if that shows outside the level
+    pub fn viewport_at(&self, center: Vec2) -> Vec4 {
+        let visible = self.visible_size();
+        let shift = -center / visible * 2.;
+        let fract = 1. / visible * 2.;
+        Vec4::from((shift, fract))
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(uvec2(VIEWPORT_SIZE.x as u32, VIEWPORT_SIZE.y as u32))
+    }
+}
+
+/// Zooms in by `steps` [`ZOOM_STEP`]s, or out if negative
+pub fn step_zoom(zoom: f32, steps: i32) -> f32 {
+    (zoom * ZOOM_STEP.powi(steps)).clamp(MIN_ZOOM, MAX_ZOOM)
+}
+
+/// Tuning of [`CameraFollow`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FollowConfig {
+    /// half size of the box around the camera target the player can move in without moving it
+    pub deadzone: Vec2,
+    /// how many ticks of the player's velocity the camera looks ahead
+    pub look_ahead: Vec2,
+    pub max_look_ahead: Vec2,
+    /// roughly the seconds it takes to catch up with the target
+    pub smooth_time: f32,
+    /// max offset of the screen shake at full trauma
+    pub max_shake: Vec2,
+    /// trauma lost per second
+    pub trauma_decay: f32,
+    pub death_trauma: f32,
+    pub pop_trauma: f32,
+}
+
+impl Default for FollowConfig {
+    fn default() -> Self {
+        Self {
+            deadzone: vec2(40., 60.),
+            look_ahead: vec2(20., 4.),
+            max_look_ahead: vec2(120., 60.),
+            smooth_time: 0.25,
+            max_shake: vec2(12., 10.),
+            trauma_decay: 1.5,
+            death_trauma: 0.6,
+            pop_trauma: 0.25,
+        }
+    }
+}
+
+/// Smoothly follows the player instead of being locked to it, and shakes on impactful events
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct CameraFollow {
+    pub config: FollowConfig,
+    /// the center of the deadzone, trails the player
+    anchor: Vec2,
+    /// the smoothed center of the camera, without shake
+    pub pos: Vec2,
+    vel: Vec2,
+    /// `0..=1`, the shake grows with its square
+    pub trauma: f32,
+    time: f32,
+    /// to snap instead of smoothly scrolling to a freshly loaded or restarted level
+    level_hash: u64,
+    ticks: u32,
+}
+
+impl CameraFollow {
+    pub fn update(&mut self, dt: f32, camera: &Camera, game: &Game, events: &[GameEvent]) {
+        let player = game.player.pos;
+        let config = self.config;
+        if game.level.hash != self.level_hash || game.ticks < self.ticks {
+            self.level_hash = game.level.hash;
+            self.anchor = player;
+            self.pos = camera.clamp(game.level.size, player);
+            self.vel = Vec2::ZERO;
+            self.trauma = 0.;
+        }
+        self.ticks = game.ticks;
+
+        let offset = player - self.anchor;
+        self.anchor += offset - offset.clamp(-config.deadzone, config.deadzone);
+        let look_ahead = (game.player.vel * config.look_ahead)
+            .clamp(-config.max_look_ahead, config.max_look_ahead);
+        let target = camera.clamp(game.level.size, self.anchor + look_ahead);
+        (self.pos, self.vel) = smooth_damp(self.pos, target, self.vel, config.smooth_time, dt);
+        self.pos = camera.clamp(game.level.size, self.pos);
+
+        for event in events {
+            let trauma = match event {
+                GameEvent::Death { .. } => config.death_trauma,
+                GameEvent::BubblePopped { .. } => config.pop_trauma,
+                _ => 0.,
+            };
+            self.trauma = (self.trauma + trauma).min(1.);
+        }
+        self.trauma = (self.trauma - config.trauma_decay * dt).max(0.);
+        self.time += dt;
+    }
+
+    /// the center to render from, including the shake
+    pub fn center(&self) -> Vec2 {
+        let t = self.time;
+        // a few incommensurate sines are noisy enough for a shake
+        let noise = vec2(
+            (t * 37.).sin() * 0.6 + (t * 71.).sin() * 0.4,
+            (t * 43.).sin() * 0.6 + (t * 89.).cos() * 0.4,
+        );
+        self.pos + noise * self.config.max_shake * self.trauma * self.trauma
+    }
+}
+
+/// Critically damped spring towards `target`, returning the new position and velocity
+pub fn smooth_damp(pos: Vec2, target: Vec2, vel: Vec2, smooth_time: f32, dt: f32) -> (Vec2, Vec2) {
+    let omega = 2. / smooth_time.max(0.0001);
+    let x = omega * dt;
+    let exp = 1. / (1. + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = pos - target;
+    let temp = (vel + omega * change) * dt;
+    let vel = (vel - omega * temp) * exp;
+    (target + (change + temp) * exp, vel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::assists::Assists;
+    use crate::level::Level;
+
+    fn camera(width: u32, height: u32, scaling: Scaling) -> Camera {
+        Camera {
+            scaling,
+            ..Camera::new(uvec2(width, height))
+        }
+    }
+
+    #[test]
+    fn scale_fits_the_surface() {
+        assert_eq!(camera(1600, 1200, Scaling::Free).scale(), 2.);
+        assert_eq!(camera(1000, 600, Scaling::Free).scale(), 1.);
+        assert_eq!(camera(1800, 1400, Scaling::Free).scale(), 2.25);
+        assert_eq!(camera(1800, 1400, Scaling::Integer).scale(), 2.);
+        // too small for any whole multiple
+        assert_eq!(camera(400, 300, Scaling::Integer).scale(), 0.5);
+    }
+
+    #[test]
+    fn letterbox_centers_the_viewport() {
+        assert_eq!(
+            camera(1000, 600, Scaling::Free).letterbox(),
+            (uvec2(100, 0), uvec2(800, 600))
+        );
+        assert_eq!(
+            camera(800, 1000, Scaling::Free).letterbox(),
+            (uvec2(0, 200), uvec2(800, 600))
+        );
+        assert_eq!(
+            camera(1800, 1400, Scaling::Integer).letterbox(),
+            (uvec2(100, 100), uvec2(1600, 1200))
+        );
+        // minimized windows still get a valid viewport
+        assert_eq!(
+            camera(0, 0, Scaling::Free).letterbox(),
+            (UVec2::ZERO, UVec2::ONE)
+        );
+    }
+
+    #[test]
+    fn clamp_keeps_the_view_within_the_level() {
+        let camera = Camera::default();
+        let level = uvec2(2000, 1000);
+        assert_eq!(camera.clamp(level, Vec2::ZERO), vec2(400., 300.));
+        assert_eq!(camera.clamp(level, vec2(1900., 900.)), vec2(1600., 700.));
+        assert_eq!(camera.clamp(level, vec2(1000., 500.)), vec2(1000., 500.));
+        // levels smaller than the view are centered
+        assert_eq!(camera.clamp(uvec2(400, 300), Vec2::ZERO), vec2(200., 150.));
+        assert_eq!(camera.clamp(uvec2(400, 2000), Vec2::ZERO), vec2(200., 300.));
+
+        let zoomed = Camera { zoom: 2., ..camera };
+        assert_eq!(zoomed.clamp(level, Vec2::ZERO), vec2(200., 150.));
+        assert_eq!(
+            zoomed.clamp(uvec2(400, 300), vec2(0., 300.)),
+            vec2(200., 150.)
+        );
+    }
+
+    /// a level larger than the view with the player standing still in the middle of it
+    fn game() -> Game {
+        let columns = 250;
+        let mut rows = vec![" ".repeat(columns); 150];
+        rows[75].replace_range(125..126, "S");
+        let rows = rows.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut game = Game::new(Level::from_ascii(&rows), Assists::default());
+        game.player.vel = Vec2::ZERO;
+        game
+    }
+
+    /// updates often enough for the camera to settle
+    fn settle(follow: &mut CameraFollow, game: &Game) {
+        for _ in 0..100 {
+            follow.update(0.1, &Camera::default(), game, &[]);
+        }
+    }
+
+    #[test]
+    fn snaps_to_a_new_level() {
+        let game = game();
+        let mut follow = CameraFollow::default();
+        follow.update(0.01, &Camera::default(), &game, &[]);
+        assert_eq!(follow.pos, game.player.pos);
+    }
+
+    #[test]
+    fn player_moves_freely_within_the_deadzone() {
+        let mut game = game();
+        let start = game.player.pos;
+        let mut follow = CameraFollow::default();
+        settle(&mut follow, &game);
+        game.player.pos += vec2(30., -50.);
+        settle(&mut follow, &game);
+        assert_eq!(follow.pos, start);
+
+        // leaving the deadzone drags it along
+        game.player.pos = start + vec2(100., 0.);
+        settle(&mut follow, &game);
+        let deadzone = follow.config.deadzone;
+        assert!(
+            follow
+                .pos
+                .abs_diff_eq(start + vec2(100. - deadzone.x, 0.), 0.01)
+        );
+    }
+
+    #[test]
+    fn look_ahead_is_clamped() {
+        let mut game = game();
+        let start = game.player.pos;
+        let mut follow = CameraFollow::default();
+        game.player.vel = vec2(1., 0.);
+        settle(&mut follow, &game);
+        let config = follow.config;
+        assert!(
+            follow
+                .pos
+                .abs_diff_eq(start + vec2(config.look_ahead.x, 0.), 0.01)
+        );
+
+        game.player.vel = vec2(-100., 100.);
+        settle(&mut follow, &game);
+        let max = config.max_look_ahead;
+        assert!(follow.pos.abs_diff_eq(start + vec2(-max.x, max.y), 0.01));
+    }
+
+    #[test]
+    fn snaps_on_restart() {
+        let mut game = game();
+        let start = game.player.pos;
+        let mut follow = CameraFollow::default();
+        game.ticks = 10;
+        follow.update(0.1, &Camera::default(), &game, &[]);
+        game.player.pos += vec2(300., 0.);
+        game.ticks = 20;
+        follow.update(0.1, &Camera::default(), &game, &[]);
+        assert!(follow.pos.x < start.x + 300.);
+
+        // restarting resets the ticks
+        game.ticks = 0;
+        follow.trauma = 1.;
+        follow.update(0., &Camera::default(), &game, &[]);
+        assert_eq!(follow.pos, game.player.pos);
+        assert_eq!(follow.trauma, 0.);
+    }
+
+    #[test]
+    fn trauma_adds_up_and_decays() {
+        let game = game();
+        let mut follow = CameraFollow::default();
+        let config = follow.config;
+        let death = GameEvent::Death { pos: Vec2::ZERO };
+        follow.update(0., &Camera::default(), &game, &[death]);
+        assert_eq!(follow.trauma, config.death_trauma);
+        follow.update(0., &Camera::default(), &game, &[death, death]);
+        assert_eq!(follow.trauma, 1.);
+
+        follow.update(0.2, &Camera::default(), &game, &[]);
+        assert!((follow.trauma - (1. - config.trauma_decay * 0.2)).abs() < 1e-6);
+        follow.update(10., &Camera::default(), &game, &[]);
+        assert_eq!(follow.trauma, 0.);
+        assert_eq!(follow.center(), follow.pos);
+    }
+
+    #[test]
+    fn smooth_damp_approaches_without_overshooting() {
+        let target = vec2(100., -50.);
+        let (mut pos, mut vel) = (Vec2::ZERO, Vec2::ZERO);
+        let mut last_distance = pos.distance(target);
+        for _ in 0..60 {
+            (pos, vel) = smooth_damp(pos, target, vel, 0.25, 1. / 60.);
+            let distance = pos.distance(target);
+            assert!(distance < last_distance);
+            assert!(pos.x <= target.x && pos.y >= target.y);
+            last_distance = distance;
+        }
+        assert!(last_distance < 1.);
+        assert_eq!(smooth_damp(pos, target, vel, 0.25, 0.), (pos, vel));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::entity::checkpoint::Checkpoint;
+use crate::rendering::framedata::FrameDataBinding;
+use crate::rendering::quad::{QuadRenderer, QuadVertex, QuadVertexBuffer};
+use glam::{Vec2, Vec4, vec2, vec4};
+use wgpu::RenderPass;
+
+const POLE_SIZE: Vec2 = vec2(3., 36.);
+const FLAG_SIZE: Vec2 = vec2(16., 10.);
+
+pub struct CheckpointRenderer(QuadRenderer);
+
+impl CheckpointRenderer {
+    pub fn new(quad: QuadRenderer) -> Self {
+        Self(quad)
+    }
+
+    /// active checkpoints raise a flag in `active_color`
+    pub fn draw(
+        &self,
+        rpass: &mut RenderPass,
+        frame_data: &FrameDataBinding,
+        checkpoints: &[Checkpoint],
+        active_color: Vec4,
+    ) {
+        if checkpoints.is_empty() {
+            return;
+        }
+
+        let pole_color = vec4(1., 1., 1., 1.);
+        let inactive_color = vec4(0.4, 0.4, 0.4, 1.);
+        let vertices = checkpoints
+            .iter()
+            .flat_map(|checkpoint| {
+                let pole = checkpoint.pos + vec2(-0.5, 0.) * POLE_SIZE;
+                let (flag, flag_color) = if checkpoint.active {
+                    (
+                        pole + vec2(POLE_SIZE.x, POLE_SIZE.y - FLAG_SIZE.y),
+                        active_color,
+                    )
+                } else {
+                    (pole + vec2(POLE_SIZE.x, 0.), inactive_color)
+                };
+                [
+                    QuadVertex::rect(pole, POLE_SIZE, pole_color),
+                    QuadVertex::rect(flag, FLAG_SIZE, flag_color),
+                ]
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        self.0.draw_color(
+            rpass,
+            frame_data,
+            &QuadVertexBuffer::stream(&self.0.vertex_stream, &vertices),
+        )
+    }
+}
@@ -1,13 +1,15 @@
 use crate::rendering::game_renderer::RenderConfig;
+use crate::rendering::stream_buffer::StreamBuffer;
 use bytemuck::{Pod, Zeroable};
-use glam::{UVec2, Vec2, Vec4, vec2};
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use glam::{Vec2, Vec4, vec2};
+use std::sync::{Arc, Mutex};
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingResource, BindingType, BufferBinding, BufferBindingType,
-    BufferUsages, ShaderStages,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferAddress, BufferBinding,
+    BufferBindingType, BufferSize, BufferUsages, ShaderStages,
 };
 
+/// the area the UI is laid out in and the world shown at zoom `1.`, see [`crate::rendering::camera::Camera`]
 pub const VIEWPORT_SIZE: Vec2 = vec2(800., 600.);
 
 #[repr(C)]
@@ -16,26 +18,34 @@ pub struct FrameData {
     pub viewport: Vec4,
 }
 
-pub fn get_viewport(level_size: UVec2, player: Vec2) -> Vec4 {
-    let viewport_half = VIEWPORT_SIZE * 0.5;
-    let position = player.clamp(viewport_half, level_size.as_vec2() - viewport_half);
-    let shift = -position / VIEWPORT_SIZE * 2.;
-    let fract = 1. / VIEWPORT_SIZE * 2.;
-    Vec4::from((shift, fract))
+/// viewport mapping [`VIEWPORT_SIZE`] onto the screen, with the origin in the bottom left
+pub fn screen_viewport() -> Vec4 {
+    Vec4::from((Vec2::NEG_ONE, 2. / VIEWPORT_SIZE))
 }
 
 #[derive(Debug, Clone)]
 pub struct FrameDataBindGroupLayout {
     pub config: RenderConfig,
     pub layout: BindGroupLayout,
+    uniforms: StreamBuffer,
+    /// bind group of the current uniform buffer, tagged with its generation
+    bind_group: Arc<Mutex<Option<(u64, BindGroup)>>>,
 }
 
+/// The frame data bind group together with the dynamic offset of one [`FrameData`]
 #[derive(Debug, Clone)]
-pub struct FrameDataBinding(pub BindGroup);
+pub struct FrameDataBinding {
+    pub bind_group: BindGroup,
+    pub offset: u32,
+}
+
+const FRAME_DATA_SIZE: BufferAddress = size_of::<FrameData>() as BufferAddress;
+const INITIAL_UNIFORMS: BufferAddress = 32;
 
 impl FrameDataBindGroupLayout {
     pub fn new(config: &RenderConfig) -> Self {
         let device = &config.device;
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as BufferAddress;
         Self {
             config: config.clone(),
             layout: device.create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -45,33 +55,53 @@ impl FrameDataBindGroupLayout {
                     visibility: ShaderStages::VERTEX_FRAGMENT,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                        has_dynamic_offset: true,
+                        min_binding_size: BufferSize::new(FRAME_DATA_SIZE),
                     },
                     count: None,
                 }],
             }),
+            uniforms: StreamBuffer::new(
+                config,
+                "FrameData stream",
+                BufferUsages::UNIFORM,
+                alignment,
+                alignment * INITIAL_UNIFORMS,
+            ),
+            bind_group: Arc::default(),
         }
     }
 
+    /// Starts a new frame, reusing the uniform memory of the last one
+    pub fn reset(&self) {
+        self.uniforms.reset();
+    }
+
     pub fn create_bind_group(&self, frame_data: FrameData) -> FrameDataBinding {
-        let device = &self.config.device;
-        let buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("FrammeData Buffer"),
-            contents: bytemuck::cast_slice(&[frame_data]),
-            usage: BufferUsages::UNIFORM,
-        });
-        FrameDataBinding(device.create_bind_group(&BindGroupDescriptor {
-            label: Some("FrameData Bind Group"),
-            layout: &self.layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: BindingResource::Buffer(BufferBinding {
-                    buffer: &buffer,
-                    offset: 0,
-                    size: None,
-                }),
-            }],
-        }))
+        let slice = self.uniforms.push(bytemuck::cast_slice(&[frame_data]));
+        let mut bind_group = self.bind_group.lock().unwrap();
+        let bind_group = match &*bind_group {
+            Some((generation, bind_group)) if *generation == slice.generation => bind_group.clone(),
+            _ => {
+                let new = self.config.device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("FrameData Bind Group"),
+                    layout: &self.layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer: &slice.buffer,
+                            offset: 0,
+                            size: BufferSize::new(FRAME_DATA_SIZE),
+                        }),
+                    }],
+                });
+                *bind_group = Some((slice.generation, new.clone()));
+                new
+            }
+        };
+        FrameDataBinding {
+            bind_group,
+            offset: slice.offset as u32,
+        }
     }
 }
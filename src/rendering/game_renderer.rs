@@ -1,54 +1,146 @@
 use super::splash_renderer::SplashRenderer;
 use crate::entity::game::Game;
+use crate::rendering::background_renderer::BackgroundRenderer;
 use crate::rendering::bubble_renderer::BubbleRenderer;
-use crate::rendering::framedata::{FrameData, FrameDataBindGroupLayout, get_viewport};
+use crate::rendering::camera::{Camera, CameraFollow};
+use crate::rendering::checkpoint_renderer::CheckpointRenderer;
+use crate::rendering::framedata::{FrameData, FrameDataBindGroupLayout};
 use crate::rendering::level_renderer::LevelRenderer;
+use crate::rendering::offscreen::OffscreenTarget;
+use crate::rendering::orb_renderer::OrbRenderer;
 use crate::rendering::player_renderer::PlayerRenderer;
+use crate::rendering::portal_renderer::PortalRenderer;
+use crate::rendering::post::PostProcess;
 use crate::rendering::quad::QuadRenderer;
 use crate::rendering::quad_texture::QuadTextureBindGroupLayout;
+use crate::rendering::sprite_batch::SpriteBatch;
+use crate::rendering::ui_renderer::UiRenderer;
+use crate::save::PostSettings;
+use crate::ui::Overlay;
+use glam::UVec2;
+use image::RgbaImage;
 use wgpu::{Device, Queue, TextureFormat, TextureView};
 
+/// [`SpriteBatch`] layers, the player's bubble is drawn on top of the flying paint
+const PORTAL_LAYER: i32 = 0;
+const SPLASH_LAYER: i32 = 1;
+const BUBBLE_LAYER: i32 = 2;
+
 #[derive(Debug, Clone)]
 pub struct RenderConfig {
     pub device: Device,
     pub queue: Queue,
     pub swapchain_format: TextureFormat,
+    /// the format of the intermediate target everything is drawn into before post processing
+    pub scene_format: TextureFormat,
 }
 
 pub struct GameRenderer {
     pub config: RenderConfig,
     pub quad: QuadRenderer,
     pub player: PlayerRenderer,
+    pub ghost: PlayerRenderer,
+    pub portal: PortalRenderer,
     pub splash: SplashRenderer,
     pub bubble: BubbleRenderer,
+    pub checkpoint: CheckpointRenderer,
+    pub orb: OrbRenderer,
     pub level: LevelRenderer,
+    pub background: BackgroundRenderer,
+    pub ui: UiRenderer,
+    pub show_ghost: bool,
+    pub show_outlines: bool,
+    pub camera: Camera,
+    pub follow: CameraFollow,
+    pub post: PostProcess,
+    pub post_settings: PostSettings,
+    /// seconds since startup, for animations independent of the game
+    pub time: f32,
 }
 
 impl GameRenderer {
     pub fn new(config: &RenderConfig) -> anyhow::Result<Self> {
-        let frame_data_layout = FrameDataBindGroupLayout::new(config);
-        let quad_texture_layout = QuadTextureBindGroupLayout::new(config);
-        let quad = QuadRenderer::new(config, frame_data_layout, quad_texture_layout);
+        // the world is drawn into the scene target and post processed into the output, the UI is
+        // drawn onto the output afterwards so post effects leave it alone
+        let scene_config = RenderConfig {
+            swapchain_format: config.scene_format,
+            ..config.clone()
+        };
+        let frame_data_layout = FrameDataBindGroupLayout::new(&scene_config);
+        let quad_texture_layout = QuadTextureBindGroupLayout::new(&scene_config);
+        let post = PostProcess::new(config, &quad_texture_layout);
+        let quad = QuadRenderer::new(&scene_config, frame_data_layout, quad_texture_layout);
+        let ui_quad = QuadRenderer::new(
+            config,
+            quad.frame_data_layout.clone(),
+            quad.texture_layout.clone(),
+        );
         Ok(Self {
             player: PlayerRenderer::new(quad.clone()),
+            ghost: PlayerRenderer::ghost(quad.clone()),
+            portal: PortalRenderer::new(),
             level: LevelRenderer::new(quad.clone())?,
+            background: BackgroundRenderer::new(quad.clone())?,
             splash: SplashRenderer::new(quad.clone()),
             bubble: BubbleRenderer::new(quad.clone()),
+            checkpoint: CheckpointRenderer::new(quad.clone()),
+            orb: OrbRenderer::new(quad.clone()),
+            ui: UiRenderer::new(ui_quad),
             quad,
             config: config.clone(),
+            show_ghost: true,
+            show_outlines: false,
+            camera: Camera::default(),
+            follow: CameraFollow::default(),
+            post,
+            post_settings: PostSettings::default(),
+            time: 0.,
         })
     }
 
-    pub fn draw<'a>(&self, game: &Game, output: TextureView) {
+    /// Must be called at the start of every frame, before anything is drawn
+    pub fn begin_frame(&self) {
+        self.quad.begin_frame();
+        self.ui.begin_frame();
+    }
+
+    pub fn draw(&self, game: &Game, overlay: &Overlay, output: TextureView) {
+        self.draw_with_camera(&self.camera, game, overlay, output);
+    }
+
+    /// Renders a frame into an image of `size` instead of the swapchain, blocking until it's done
+    pub fn render_image(
+        &self,
+        game: &Game,
+        overlay: &Overlay,
+        size: UVec2,
+    ) -> anyhow::Result<RgbaImage> {
+        let target = OffscreenTarget::new(&self.config, size);
+        let camera = Camera {
+            surface_size: target.size,
+            ..self.camera
+        };
+        self.draw_with_camera(&camera, game, overlay, target.view.clone());
+        target.read_image(&self.config)
+    }
+
+    fn draw_with_camera(
+        &self,
+        camera: &Camera,
+        game: &Game,
+        overlay: &Overlay,
+        output: TextureView,
+    ) {
         let device = &self.config.device;
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("main draw"),
         });
+        let targets = self.post.targets(camera.surface_size);
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &output,
+                    view: &targets.scene.texture_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -59,20 +151,144 @@ impl GameRenderer {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
+            camera.apply(&mut rpass);
 
+            let center = self.follow.center();
             let frame_data = self.quad.frame_data_layout.create_bind_group(FrameData {
-                viewport: get_viewport(game.level.size, game.player.pos),
+                viewport: camera.viewport_at(center),
             });
+            self.background.draw(
+                &mut rpass,
+                &frame_data,
+                &game.level.meta.background,
+                center,
+                camera.visible_size(),
+                self.time,
+            );
             self.level.draw(&mut rpass, &frame_data);
+            if self.show_outlines {
+                self.level.draw_outlines(&mut rpass, &frame_data);
+            }
+            self.checkpoint.draw(
+                &mut rpass,
+                &frame_data,
+                &game.checkpoints,
+                game.player.color(),
+            );
+            self.orb
+                .draw(&mut rpass, &frame_data, &game.orbs, &game.player);
+            if let Some(pos) = self
+                .level
+                .ghost()
+                .and_then(|ghost| ghost.position(game.ticks))
+            {
+                self.ghost.draw_at(&mut rpass, &frame_data, pos);
+            }
             self.player.draw(&mut rpass, &frame_data, &game.player);
-            game.portal
-                .render(&mut rpass, &frame_data, &self.quad, &game.player);
+            let mut sprites = SpriteBatch::new();
+            self.portal
+                .batch(&mut sprites, PORTAL_LAYER, &game.portal, &game.player);
             self.splash
-                .draw(&mut rpass, &frame_data, game.splashes.as_slice());
+                .batch(&mut sprites, SPLASH_LAYER, game.splashes.as_slice());
             self.bubble
-                .draw(&mut rpass, &frame_data, game.player_bubble.as_slice());
+                .batch(&mut sprites, BUBBLE_LAYER, game.player_bubble.as_slice());
+            sprites.draw(&self.quad, &mut rpass, &frame_data);
+        }
+        self.post
+            .apply(&mut encoder, &targets, self.post_settings, &output);
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("ui"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &output,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            camera.apply(&mut rpass);
+            self.ui.draw(&mut rpass, overlay);
         }
 
         self.config.queue.submit(Some(encoder.finish()));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::assists::Assists;
+    use crate::level::Level;
+    use crate::paint::SplatStamp;
+    use crate::rendering::offscreen::headless_config;
+    use crate::save::MemorySaveStore;
+    use glam::{ivec2, vec2, vec4};
+
+    /// rerun with `UPDATE_GOLDEN=1` to accept a changed rendering
+    const GOLDEN: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/rendering/golden/renders_headless.png"
+    );
+    /// rasterizers and shader backends round differently, channels may differ this much
+    const CHANNEL_TOLERANCE: u8 = 8;
+    /// and this fraction of the pixels may differ by more, for edges that snap differently
+    const PIXEL_TOLERANCE: f32 = 0.01;
+
+    #[test]
+    fn renders_headless() {
+        let config = pollster::block_on(headless_config(true)).unwrap();
+        let mut renderer = GameRenderer::new(&config).unwrap();
+        let level = Level::load_file_tree().unwrap().remove(0);
+        renderer
+            .level
+            .load_level(level.clone(), &MemorySaveStore::default());
+        let game = Game::new(level, Assists::default());
+        renderer.follow.pos = game.player.pos;
+        renderer.begin_frame();
+        // paint the floor below the player, so the masked paint shows up
+        let start = game.player.pos.as_ivec2();
+        let floor = (0..start.y)
+            .rev()
+            .find(|y| game.level.is_hit(ivec2(start.x, *y)))
+            .unwrap();
+        renderer.level.draw_color_splashes(
+            &[SplatStamp {
+                pos: vec2(start.x as f32 + 20., floor as f32),
+                size: 30.,
+                rotation: 0.5,
+                texture: 1,
+                color: vec4(1., 0.2, 0.4, 1.),
+            }],
+            &[],
+        );
+        let image = renderer
+            .render_image(&game, &Overlay::default(), UVec2::new(320, 240))
+            .unwrap();
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            image.save(GOLDEN).unwrap();
+            return;
+        }
+        let golden = image::open(GOLDEN).unwrap().into_rgba8();
+        assert_eq!(image.dimensions(), golden.dimensions());
+        let differing = image
+            .pixels()
+            .zip(golden.pixels())
+            .filter(|(a, b)| {
+                a.0.iter()
+                    .zip(b.0)
+                    .any(|(a, b)| a.abs_diff(b) > CHANNEL_TOLERANCE)
+            })
+            .count();
+        let allowed = (image.len() / 4) as f32 * PIXEL_TOLERANCE;
+        assert!(
+            differing as f32 <= allowed,
+            "{differing} pixels differ from {GOLDEN}"
+        );
+    }
+}
@@ -1,13 +1,14 @@
-use crate::entity::splash::Splash;
+use crate::drips::DripStroke;
+use crate::ghost::Ghost;
 use crate::level::Level;
+use crate::paint::{SPLAT_TEXTURES, SplatStamp};
+use crate::rendering::atlas::TextureRegion;
 use crate::rendering::framedata::{FrameData, FrameDataBinding};
+use crate::rendering::post::LEVEL_PAINT_GLOW;
 use crate::rendering::quad::{QuadRenderer, QuadVertex, QuadVertexBuffer};
 use crate::rendering::quad_texture::QuadTexture;
-use glam::{Mat2, Vec2, Vec4, vec2, vec4};
-use image::{ImageFormat, ImageReader};
-use rand::distr::Open01;
-use rand::{Rng, rng};
-use std::io::Cursor;
+use crate::save::SaveStore;
+use glam::{Vec2, Vec3, Vec4, vec2, vec4};
 use std::sync::Arc;
 use wgpu::util::{DeviceExt, TextureDataOrder};
 use wgpu::{
@@ -18,50 +19,28 @@ pub const DEBUG_DRAW_LEVEL: bool = false;
 
 pub struct LevelRenderer {
     quad: QuadRenderer,
-    splashes: Vec<QuadTexture>,
+    splashes: Vec<TextureRegion>,
     loaded: Option<LoadedLevel>,
 }
 
 pub struct LoadedLevel {
     level: Arc<Level>,
     vertices: QuadVertexBuffer,
+    /// the level quad tinted by [`LEVEL_PAINT_GLOW`]
+    paint_vertices: QuadVertexBuffer,
     level_texture: QuadTexture,
     collision_mask: QuadTexture,
+    outline: QuadTexture,
+    ghost: Option<Ghost>,
 }
 
 impl LevelRenderer {
     pub fn new(quad: QuadRenderer) -> anyhow::Result<Self> {
-        let splashes = [
-            include_bytes!(concat!(
-                env!("CARGO_MANIFEST_DIR"),
-                "/assets/colorsplash/1.png"
-            ))
-            .as_slice(),
-            include_bytes!(concat!(
-                env!("CARGO_MANIFEST_DIR"),
-                "/assets/colorsplash/2.png"
-            ))
-            .as_slice(),
-            include_bytes!(concat!(
-                env!("CARGO_MANIFEST_DIR"),
-                "/assets/colorsplash/3.png"
-            ))
-            .as_slice(),
-        ];
-        let splashes = splashes
+        // the level texture pass is y-down
+        let splashes = SPLAT_TEXTURES
             .iter()
-            .map(|bytes| {
-                let image = ImageReader::with_format(Cursor::new(*bytes), ImageFormat::Png)
-                    .decode()?
-                    .flipv()
-                    .into_rgba8();
-                Ok(QuadTexture::upload(
-                    &quad.config,
-                    &quad.texture_layout,
-                    &image,
-                ))
-            })
-            .collect::<anyhow::Result<Vec<_>>>()?;
+            .map(|bytes| quad.load_texture(bytes).flip_y())
+            .collect();
         Ok(Self {
             quad,
             splashes,
@@ -69,32 +48,17 @@ impl LevelRenderer {
         })
     }
 
-    pub fn load_level(&mut self, level: Arc<Level>) {
+    /// Loads the level together with the ghost of its best run from `store`
+    pub fn load_level(&mut self, level: Arc<Level>, store: &dyn SaveStore) {
         let size = level.size.as_vec2();
         let vertices = QuadVertexBuffer::new(
             &self.quad.config,
-            &[
-                QuadVertex {
-                    position: vec2(0., 0.) * size,
-                    tex_coord: vec2(0., 0.),
-                    vtx_color: vec4(1., 1., 1., 1.),
-                },
-                QuadVertex {
-                    position: vec2(0., 1.) * size,
-                    tex_coord: vec2(0., 1.),
-                    vtx_color: vec4(1., 1., 1., 1.),
-                },
-                QuadVertex {
-                    position: vec2(1., 0.) * size,
-                    tex_coord: vec2(1., 0.),
-                    vtx_color: vec4(1., 1., 1., 1.),
-                },
-                QuadVertex {
-                    position: vec2(1., 1.) * size,
-                    tex_coord: vec2(1., 1.),
-                    vtx_color: vec4(1., 1., 1., 1.),
-                },
-            ],
+            &QuadVertex::rect(Vec2::ZERO, size, vec4(1., 1., 1., 1.)),
+        );
+        // brighter than white, so saturated paint blooms
+        let paint_vertices = QuadVertexBuffer::new(
+            &self.quad.config,
+            &QuadVertex::rect(Vec2::ZERO, size, Vec3::splat(LEVEL_PAINT_GLOW).extend(1.)),
         );
 
         let device = &self.quad.config.device;
@@ -115,17 +79,20 @@ impl LevelRenderer {
                 usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
                 view_formats: &[],
             };
-            let texture = if DEBUG_DRAW_LEVEL {
-                device.create_texture_with_data(
-                    &self.quad.config.queue,
-                    &texture_descriptor,
-                    TextureDataOrder::MipMajor,
-                    &image.as_raw(),
-                )
-            } else {
-                device.create_texture(&texture_descriptor)
-            };
-            QuadTexture::new(&self.quad.config, &self.quad.texture_layout, texture)
+            let texture = QuadTexture::new(
+                &self.quad.config,
+                &self.quad.texture_layout,
+                device.create_texture(&texture_descriptor),
+            );
+            if DEBUG_DRAW_LEVEL {
+                // drawn instead of uploaded, as the texture may be in a float format
+                self.draw_image(
+                    &texture,
+                    &QuadTexture::upload(&self.quad.config, &self.quad.texture_layout, image),
+                    level.size.as_vec2(),
+                );
+            }
+            texture
         };
 
         let collision_mask = {
@@ -153,27 +120,103 @@ impl LevelRenderer {
             QuadTexture::new(&self.quad.config, &self.quad.texture_layout, texture)
         };
 
+        let outline = QuadTexture::upload(
+            &self.quad.config,
+            &self.quad.texture_layout,
+            &level.outline(),
+        );
+
         self.loaded = Some(LoadedLevel {
             vertices,
+            paint_vertices,
+            outline,
+            ghost: Ghost::load_best(store, &level),
             level,
             level_texture,
             collision_mask,
         });
     }
 
+    /// Covers `target` with `image`, keeping its rows
+    fn draw_image(&self, target: &QuadTexture, image: &QuadTexture, size: Vec2) {
+        let device = &self.quad.config.device;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("level image draw"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            let frame_data = self.quad.frame_data_layout.create_bind_group(FrameData {
+                viewport: Vec4::from((Vec2::NEG_ONE, 1. / size * 2.)),
+            });
+            // the level texture pass is y-down
+            let vertices =
+                [vec2(0., 0.), vec2(0., 1.), vec2(1., 0.), vec2(1., 1.)].map(|corner| QuadVertex {
+                    position: corner * size,
+                    tex_coord: vec2(corner.x, 1. - corner.y),
+                    vtx_color: Vec4::ONE,
+                });
+            self.quad.draw_texture(
+                &mut rpass,
+                &frame_data,
+                &QuadVertexBuffer::stream(&self.quad.vertex_stream, &vertices),
+                image,
+            );
+        }
+        self.quad.config.queue.submit(Some(encoder.finish()));
+    }
+
     pub fn unload_level(&mut self) {
         self.loaded = None;
     }
 
+    /// Reloads the ghost of the best run on the loaded level from `store`
+    pub fn reload_ghost(&mut self, store: &dyn SaveStore) {
+        if let Some(loaded) = &mut self.loaded {
+            loaded.ghost = Ghost::load_best(store, &loaded.level);
+        }
+    }
+
+    /// the best run on the loaded level, if there is one
+    pub fn ghost(&self) -> Option<&Ghost> {
+        self.loaded.as_ref()?.ghost.as_ref()
+    }
+
     pub fn draw(&self, rpass: &mut RenderPass, frame_data: &FrameDataBinding) {
+        if let Some(loaded) = &self.loaded {
+            self.quad.draw_texture(
+                rpass,
+                frame_data,
+                &loaded.paint_vertices,
+                &loaded.level_texture,
+            )
+        }
+    }
+
+    /// faint outlines of the platforms, for the outlines assist
+    pub fn draw_outlines(&self, rpass: &mut RenderPass, frame_data: &FrameDataBinding) {
         if let Some(loaded) = &self.loaded {
             self.quad
-                .draw_texture(rpass, frame_data, &loaded.vertices, &loaded.level_texture)
+                .draw_texture(rpass, frame_data, &loaded.vertices, &loaded.outline)
         }
     }
 
-    pub fn draw_color_splashes(&mut self, bubbles: &[Splash]) {
-        if bubbles.is_empty() {
+    /// Paints the stamps and drip strokes onto the level texture, the same way
+    /// [`crate::paint::RevealMask`] does on the CPU
+    pub fn draw_color_splashes(&self, stamps: &[SplatStamp], strokes: &[DripStroke]) {
+        if stamps.is_empty() && strokes.is_empty() {
             return;
         }
 
@@ -202,48 +245,80 @@ impl LevelRenderer {
                     viewport: Vec4::from((Vec2::NEG_ONE, 1. / loaded.level.size.as_vec2() * 2.)),
                 });
 
-                let mut rng = rng();
-                for b in bubbles {
-                    let size = 20. + 10. * rng.sample::<f32, _>(Open01);
-                    let pos = vec2(b.pos.x, loaded.level.size.y as f32 - b.pos.y);
-                    let texture = &self.splashes[rng.random_range(0..self.splashes.len())];
-                    let vtx_color = b.color;
-                    let rot = Mat2::from_angle(rng.sample(Open01));
+                for stamp in stamps {
+                    let vtx_color = stamp.color;
+                    let splash = &self.splashes[stamp.texture];
+                    let [c00, c01, c10, c11] = stamp.corners(loaded.level.size.y);
                     self.quad.draw_masked(
                         &mut rpass,
                         &frame_data,
-                        &QuadVertexBuffer::new(
-                            &self.quad.config,
+                        &QuadVertexBuffer::stream(
+                            &self.quad.vertex_stream,
                             &[
                                 QuadVertex {
-                                    position: rot * vec2(-1., -1.) * size + pos,
-                                    tex_coord: vec2(0., 0.),
+                                    position: c00,
+                                    tex_coord: splash.uv(vec2(0., 0.)),
                                     vtx_color,
                                 },
                                 QuadVertex {
-                                    position: rot * vec2(-1., 1.) * size + pos,
-                                    tex_coord: vec2(0., 1.),
+                                    position: c01,
+                                    tex_coord: splash.uv(vec2(0., 1.)),
                                     vtx_color,
                                 },
                                 QuadVertex {
-                                    position: rot * vec2(1., -1.) * size + pos,
-                                    tex_coord: vec2(1., 0.),
+                                    position: c10,
+                                    tex_coord: splash.uv(vec2(1., 0.)),
                                     vtx_color,
                                 },
                                 QuadVertex {
-                                    position: rot * vec2(1., 1.) * size + pos,
-                                    tex_coord: vec2(1., 1.),
+                                    position: c11,
+                                    tex_coord: splash.uv(vec2(1., 1.)),
                                     vtx_color,
                                 },
                             ],
                         ),
-                        texture,
+                        &splash.texture,
                         &loaded.collision_mask,
                     );
                 }
+                self.draw_drips(&mut rpass, &frame_data, loaded, strokes);
             }
 
             self.quad.config.queue.submit(Some(encoder.finish()));
         }
     }
+
+    /// Drips are solid rects, sampled from the opaque center of a splat
+    fn draw_drips(
+        &self,
+        rpass: &mut RenderPass,
+        frame_data: &FrameDataBinding,
+        loaded: &LoadedLevel,
+        strokes: &[DripStroke],
+    ) {
+        if strokes.is_empty() {
+            return;
+        }
+        let splash = &self.splashes[0];
+        let tex_coord = splash.uv(vec2(0.5, 0.5));
+        let vertices = strokes
+            .iter()
+            .flat_map(|stroke| {
+                stroke
+                    .corners(loaded.level.size.y)
+                    .map(|position| QuadVertex {
+                        position,
+                        tex_coord,
+                        vtx_color: stroke.color,
+                    })
+            })
+            .collect::<Vec<_>>();
+        self.quad.draw_masked(
+            rpass,
+            frame_data,
+            &QuadVertexBuffer::stream(&self.quad.vertex_stream, &vertices),
+            &splash.texture,
+            &loaded.collision_mask,
+        );
+    }
 }
@@ -1,8 +1,20 @@
+pub mod atlas;
+pub mod background_renderer;
 pub mod bubble_renderer;
+pub mod camera;
+pub mod checkpoint_renderer;
 pub mod framedata;
 pub mod game_renderer;
 pub mod level_renderer;
+pub mod offscreen;
+pub mod orb_renderer;
 pub mod player_renderer;
+pub mod portal_renderer;
+pub mod post;
 pub mod quad;
 pub mod quad_texture;
+pub mod software;
 pub mod splash_renderer;
+pub mod sprite_batch;
+pub mod stream_buffer;
+pub mod ui_renderer;
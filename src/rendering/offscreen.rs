@@ -0,0 +1,157 @@
+use crate::rendering::game_renderer::RenderConfig;
+use crate::rendering::post;
+use anyhow::Context;
+use glam::UVec2;
+use image::RgbaImage;
+use wgpu::{
+    BufferDescriptor, BufferUsages, COPY_BYTES_PER_ROW_ALIGNMENT, Extent3d, MapMode, Origin3d,
+    PollType, TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor,
+};
+
+/// the format offscreen renderers created by [`headless_config`] render to
+pub const OFFSCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// Creates a device without a window, `force_fallback_adapter` selects a software rasterizer so it
+/// works on machines without a GPU
+pub async fn headless_config(force_fallback_adapter: bool) -> anyhow::Result<RenderConfig> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter,
+            compatible_surface: None,
+        })
+        .await
+        .context("Failed to find an appropriate adapter")?;
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                .using_resolution(adapter.limits()),
+            memory_hints: wgpu::MemoryHints::MemoryUsage,
+            trace: Default::default(),
+        })
+        .await
+        .context("Failed to create device")?;
+    Ok(RenderConfig {
+        device,
+        queue,
+        swapchain_format: OFFSCREEN_FORMAT,
+        scene_format: post::scene_format(&adapter),
+    })
+}
+
+/// A texture to render into instead of the swapchain, which can be read back to the CPU
+#[derive(Debug, Clone)]
+pub struct OffscreenTarget {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub size: UVec2,
+}
+
+impl OffscreenTarget {
+    /// uses the [`RenderConfig::swapchain_format`], as that's what all pipelines render to
+    pub fn new(config: &RenderConfig, size: UVec2) -> Self {
+        let size = size.max(UVec2::ONE);
+        let texture = config.device.create_texture(&TextureDescriptor {
+            label: Some("offscreen target"),
+            size: Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: config.swapchain_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            size,
+        }
+    }
+
+    /// Copies the texture back to the CPU, blocking until all previously submitted work finished
+    pub fn read_image(&self, config: &RenderConfig) -> anyhow::Result<RgbaImage> {
+        let format = self.texture.format();
+        let swizzle = match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            _ => anyhow::bail!("can't read back textures of format {format:?}"),
+        };
+        let row_bytes = self.size.x * 4;
+        let padded_row_bytes = row_bytes.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = config.device.create_buffer(&BufferDescriptor {
+            label: Some("offscreen readback"),
+            size: (padded_row_bytes * self.size.y) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = config
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("offscreen readback"),
+            });
+        encoder.copy_texture_to_buffer(
+            TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(self.size.y),
+                },
+            },
+            self.texture.size(),
+        );
+        config.queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| drop(sender.send(result)));
+        config.device.poll(PollType::Wait)?;
+        receiver.recv()??;
+
+        let mut pixels = Vec::with_capacity((row_bytes * self.size.y) as usize);
+        {
+            let mapped = buffer.slice(..).get_mapped_range();
+            for row in mapped.chunks(padded_row_bytes as usize) {
+                pixels.extend_from_slice(&row[..row_bytes as usize]);
+            }
+        }
+        buffer.unmap();
+        if swizzle {
+            pixels.chunks_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+        RgbaImage::from_raw(self.size.x, self.size.y, pixels).context("readback size mismatch")
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Saves `image` as a png named after the current time into [`SCREENSHOT_DIR`]
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_screenshot(image: &RgbaImage) -> anyhow::Result<std::path::PathBuf> {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis();
+    std::fs::create_dir_all(SCREENSHOT_DIR)?;
+    let path = std::path::Path::new(SCREENSHOT_DIR).join(format!("screenshot-{millis}.png"));
+    image.save(&path)?;
+    Ok(path)
+}
@@ -0,0 +1,58 @@
+use crate::entity::orb::{Orb, OrbKind};
+use crate::entity::player::Player;
+use crate::rendering::framedata::FrameDataBinding;
+use crate::rendering::quad::{QuadRenderer, QuadVertex, QuadVertexBuffer};
+use glam::{Mat2, Vec2, Vec4, vec4};
+use std::f32::consts::FRAC_PI_4;
+use wgpu::RenderPass;
+
+const OUTER_SIZE: f32 = 9.;
+const INNER_SIZE: f32 = 6.;
+
+pub struct OrbRenderer(QuadRenderer);
+
+impl OrbRenderer {
+    pub fn new(quad: QuadRenderer) -> Self {
+        Self(quad)
+    }
+
+    /// Draws uncollected orbs as diamonds in the color the player would get from them, shift orbs
+    /// have a black rim to tell them apart
+    pub fn draw(
+        &self,
+        rpass: &mut RenderPass,
+        frame_data: &FrameDataBinding,
+        orbs: &[Orb],
+        player: &Player,
+    ) {
+        let vertices = orbs
+            .iter()
+            .filter(|orb| !orb.collected)
+            .flat_map(|orb| {
+                let rim = match orb.kind {
+                    OrbKind::Lock { .. } => vec4(1., 1., 1., 1.),
+                    OrbKind::Shift { .. } => vec4(0., 0., 0., 1.),
+                };
+                let color = Player::hue_color(orb.target_hue(player.hsv_hue));
+                [
+                    diamond(orb.pos, OUTER_SIZE, rim),
+                    diamond(orb.pos, INNER_SIZE, color),
+                ]
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        if vertices.is_empty() {
+            return;
+        }
+        self.0.draw_color(
+            rpass,
+            frame_data,
+            &QuadVertexBuffer::stream(&self.0.vertex_stream, &vertices),
+        )
+    }
+}
+
+fn diamond(pos: Vec2, size: f32, vtx_color: Vec4) -> [QuadVertex; 4] {
+    let rot = Mat2::from_angle(FRAC_PI_4);
+    QuadVertex::quad(vtx_color, |corner| rot * (corner * 2. - 1.) * size + pos)
+}
@@ -1,50 +1,47 @@
 use crate::entity::player::Player;
 use crate::rendering::framedata::FrameDataBinding;
 use crate::rendering::quad::{QuadRenderer, QuadVertex, QuadVertexBuffer};
-use glam::{vec2, vec4};
+use glam::{Vec2, Vec4, vec2, vec4};
 use wgpu::RenderPass;
 
-pub struct PlayerRenderer(QuadRenderer);
+pub const GHOST_TINT: Vec4 = vec4(0.6, 0.8, 1., 0.35);
+
+pub struct PlayerRenderer {
+    quad: QuadRenderer,
+    tint: Vec4,
+}
 
 impl PlayerRenderer {
     pub fn new(quad: QuadRenderer) -> Self {
-        Self(quad)
+        Self {
+            quad,
+            tint: vec4(1., 1., 1., 1.),
+        }
+    }
+
+    /// a translucent variant for drawing ghosts of previous runs
+    pub fn ghost(quad: QuadRenderer) -> Self {
+        Self {
+            quad,
+            tint: GHOST_TINT,
+        }
     }
 
     pub fn draw(&self, rpass: &mut RenderPass, frame_data: &FrameDataBinding, player: &Player) {
         if player.hidden {
             return;
         }
+        self.draw_at(rpass, frame_data, player.pos);
+    }
 
+    pub fn draw_at(&self, rpass: &mut RenderPass, frame_data: &FrameDataBinding, pos: Vec2) {
         let size = vec2(28., 28.);
-        let vtx_color = vec4(1., 1., 1., 1.);
-        self.0.draw_color(
+        self.quad.draw_color(
             rpass,
             frame_data,
-            &QuadVertexBuffer::new(
-                &self.0.config,
-                &[
-                    QuadVertex {
-                        position: vec2(-0.5, 0.) * size + player.pos,
-                        tex_coord: vec2(0., 0.),
-                        vtx_color,
-                    },
-                    QuadVertex {
-                        position: vec2(-0.5, 1.) * size + player.pos,
-                        tex_coord: vec2(0., 1.),
-                        vtx_color,
-                    },
-                    QuadVertex {
-                        position: vec2(0.5, 0.) * size + player.pos,
-                        tex_coord: vec2(1., 0.),
-                        vtx_color,
-                    },
-                    QuadVertex {
-                        position: vec2(0.5, 1.) * size + player.pos,
-                        tex_coord: vec2(1., 1.),
-                        vtx_color,
-                    },
-                ],
+            &QuadVertexBuffer::stream(
+                &self.quad.vertex_stream,
+                &QuadVertex::rect(pos - vec2(size.x * 0.5, 0.), size, self.tint),
             ),
         )
     }
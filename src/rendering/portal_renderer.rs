@@ -0,0 +1,79 @@
+use crate::entity::player::Player;
+use crate::entity::portal::Portal;
+use crate::rendering::quad::QuadVertex;
+use crate::rendering::sprite_batch::{SpriteBatch, SpriteMaterial};
+use glam::{Vec4, vec2};
+
+#[derive(Default)]
+pub struct PortalRenderer;
+
+impl PortalRenderer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn batch(&self, batch: &mut SpriteBatch, layer: i32, portal: &Portal, player: &Player) {
+        let white = Vec4::splat(1.0);
+        let color = player.color();
+
+        let add = if let Some(tick) = portal.tick {
+            tick as f32 / 5.
+        } else {
+            0.
+        };
+        let add2 = add * 2.;
+
+        batch.push(
+            layer,
+            SpriteMaterial::Color,
+            [
+                QuadVertex {
+                    position: vec2(-15. + add, 40. + add2) + portal.pos,
+                    tex_coord: Default::default(),
+                    vtx_color: white,
+                },
+                QuadVertex {
+                    position: vec2(15. - add, 40. + add2) + portal.pos,
+                    tex_coord: Default::default(),
+                    vtx_color: white,
+                },
+                QuadVertex {
+                    position: vec2(-15. + add, 0.) + portal.pos,
+                    tex_coord: Default::default(),
+                    vtx_color: white,
+                },
+                QuadVertex {
+                    position: vec2(15. - add, 0.) + portal.pos,
+                    tex_coord: Default::default(),
+                    vtx_color: white,
+                },
+            ],
+        );
+        batch.push(
+            layer,
+            SpriteMaterial::Color,
+            [
+                QuadVertex {
+                    position: vec2(-12. + add, 37. + add2) + portal.pos,
+                    tex_coord: Default::default(),
+                    vtx_color: color,
+                },
+                QuadVertex {
+                    position: vec2(12. - add, 37. + add2) + portal.pos,
+                    tex_coord: Default::default(),
+                    vtx_color: color,
+                },
+                QuadVertex {
+                    position: vec2(-12. + add, 3.) + portal.pos,
+                    tex_coord: Default::default(),
+                    vtx_color: color,
+                },
+                QuadVertex {
+                    position: vec2(12. - add, 3.) + portal.pos,
+                    tex_coord: Default::default(),
+                    vtx_color: color,
+                },
+            ],
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use crate::rendering::game_renderer::RenderConfig;
+use crate::rendering::quad_texture::{QuadTexture, QuadTextureBindGroupLayout};
+use crate::save::PostSettings;
+use bytemuck::{Pod, Zeroable};
+use glam::{UVec2, Vec3, Vec4, vec4};
+use image::{Rgba, RgbaImage};
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+use wgpu::util::{BufferInitDescriptor, DeviceExt, TextureDataOrder};
+use wgpu::{
+    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, ColorTargetState,
+    CommandEncoder, Extent3d, FragmentState, RenderPipeline, ShaderStages, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureFormatFeatureFlags, TextureUsages, TextureView,
+};
+
+/// the scene is rendered into this format if the adapter can blend and filter it
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+pub const LDR_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// colors above this bloom, so only HDR scenes can bloom at all
+pub const BLOOM_THRESHOLD: f32 = 1.;
+pub const BLOOM_INTENSITY: f32 = 0.8;
+/// how far the blur reaches, in texels of the half resolution bloom target
+const BLOOM_SPREAD: f32 = 1.5;
+pub const VIGNETTE_STRENGTH: f32 = 0.35;
+pub const COLOR_GRADING_STRENGTH: f32 = 1.;
+/// how much brighter than white flying paint is drawn, to make it bloom
+pub const PAINT_GLOW: f32 = 2.5;
+/// how much brighter than white the paint on the level is drawn, less than [`PAINT_GLOW`] so only
+/// saturated channels of it bloom, softly
+pub const LEVEL_PAINT_GLOW: f32 = 1.3;
+
+/// edge length of the color grading cube, see [`default_lut`]
+pub const LUT_SIZE: u32 = 16;
+
+/// [`HDR_FORMAT`] if `adapter` supports rendering to it, otherwise [`LDR_FORMAT`]
+pub fn scene_format(adapter: &Adapter) -> TextureFormat {
+    let features = adapter.get_texture_format_features(HDR_FORMAT);
+    let usages = TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+    let flags = TextureFormatFeatureFlags::FILTERABLE | TextureFormatFeatureFlags::BLENDABLE;
+    if features.allowed_usages.contains(usages) && features.flags.contains(flags) {
+        HDR_FORMAT
+    } else {
+        LDR_FORMAT
+    }
+}
+
+/// A lookup table of [`LUT_SIZE`]³ colors, stored as blue slices side by side, slightly warming
+/// and saturating the image
+pub fn default_lut() -> RgbaImage {
+    let max = (LUT_SIZE - 1) as f32;
+    RgbaImage::from_fn(LUT_SIZE * LUT_SIZE, LUT_SIZE, |x, y| {
+        let color = Vec3::new((x % LUT_SIZE) as f32, y as f32, (x / LUT_SIZE) as f32) / max;
+        let luma = color.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+        let saturated = Vec3::splat(luma).lerp(color, 1.15);
+        let warm = saturated * Vec3::new(1.04, 1., 0.94);
+        let rgb = (warm.clamp(Vec3::ZERO, Vec3::ONE) * 255.).round();
+        Rgba([rgb.x as u8, rgb.y as u8, rgb.z as u8, 255])
+    })
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Zeroable, Pod)]
+struct PostParams {
+    /// bloom threshold, bloom intensity, vignette strength, color grading strength
+    settings: Vec4,
+    /// size of a texel of the input in xy, blur step in texels in zw
+    texel: Vec4,
+}
+
+/// A uniform [`PostParams`] buffer with its bind group
+#[derive(Debug, Clone)]
+struct ParamsBinding {
+    buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+/// Render targets matching the size of the surface
+#[derive(Debug, Clone)]
+pub struct PostTargets {
+    pub size: UVec2,
+    /// everything is drawn into this first
+    pub scene: QuadTexture,
+    /// half resolution, blurred back and forth between both
+    bloom: [QuadTexture; 2],
+}
+
+/// Draws the scene into an intermediate target and applies bloom, a vignette and color grading while
+/// copying it to the output
+#[derive(Debug, Clone)]
+pub struct PostProcess {
+    config: RenderConfig,
+    texture_layout: QuadTextureBindGroupLayout,
+    extract_pipeline: RenderPipeline,
+    blur_pipeline: RenderPipeline,
+    composite_pipeline: RenderPipeline,
+    extract_params: ParamsBinding,
+    blur_params: [ParamsBinding; 2],
+    composite_params: ParamsBinding,
+    lut: QuadTexture,
+    targets: Arc<Mutex<Option<PostTargets>>>,
+}
+
+impl PostProcess {
+    /// `config` is the one of the output, the scene is rendered in [`RenderConfig::scene_format`]
+    pub fn new(config: &RenderConfig, texture_layout: &QuadTextureBindGroupLayout) -> Self {
+        let device = &config.device;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("post shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(env!(
+                "COLORBUBBLE_WGSL_SHADER_PATH"
+            )))),
+        });
+        let params_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("post params layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline = |entry_point: &str, format: TextureFormat, textures: usize| {
+            let bind_group_layouts = [&params_layout]
+                .into_iter()
+                .chain(std::iter::repeat_n(&texture_layout.layout, textures))
+                .collect::<Vec<_>>();
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(
+                    &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: None,
+                        bind_group_layouts: &bind_group_layouts,
+                        push_constant_ranges: &[],
+                    }),
+                ),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_fullscreen"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    compilation_options: Default::default(),
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: Default::default(),
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        let params = |label: &str| {
+            let buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::bytes_of(&PostParams::zeroed()),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            });
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout: &params_layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            ParamsBinding { buffer, bind_group }
+        };
+
+        let lut_image = default_lut();
+        // graded in linear space, so the table must not be decoded as sRGB
+        let lut = device.create_texture_with_data(
+            &config.queue,
+            &TextureDescriptor {
+                label: Some("color grading lut"),
+                size: Extent3d {
+                    width: lut_image.width(),
+                    height: lut_image.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8Unorm,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            TextureDataOrder::MipMajor,
+            lut_image.as_raw(),
+        );
+
+        Self {
+            extract_pipeline: pipeline("fs_bloom_extract", config.scene_format, 1),
+            blur_pipeline: pipeline("fs_blur", config.scene_format, 1),
+            composite_pipeline: pipeline("fs_composite", config.swapchain_format, 3),
+            extract_params: params("bloom extract params"),
+            blur_params: [
+                params("horizontal blur params"),
+                params("vertical blur params"),
+            ],
+            composite_params: params("composite params"),
+            lut: QuadTexture::new(config, texture_layout, lut),
+            texture_layout: texture_layout.clone(),
+            config: config.clone(),
+            targets: Arc::default(),
+        }
+    }
+
+    /// The targets for an output of `size`, recreating them if the size changed
+    pub fn targets(&self, size: UVec2) -> PostTargets {
+        let size = size.max(UVec2::ONE);
+        let mut targets = self.targets.lock().unwrap();
+        match &*targets {
+            Some(targets) if targets.size == size => targets.clone(),
+            _ => {
+                let bloom_size = (size / 2).max(UVec2::ONE);
+                let new = PostTargets {
+                    size,
+                    scene: self.create_target("scene target", size),
+                    bloom: [
+                        self.create_target("bloom target", bloom_size),
+                        self.create_target("bloom target", bloom_size),
+                    ],
+                };
+                *targets = Some(new.clone());
+                new
+            }
+        }
+    }
+
+    fn create_target(&self, label: &str, size: UVec2) -> QuadTexture {
+        let texture = self.config.device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.config.scene_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        QuadTexture::new(&self.config, &self.texture_layout, texture)
+    }
+
+    /// Applies the enabled effects to [`PostTargets::scene`], writing the result to `output`
+    pub fn apply(
+        &self,
+        encoder: &mut CommandEncoder,
+        targets: &PostTargets,
+        settings: PostSettings,
+        output: &TextureView,
+    ) {
+        let queue = &self.config.queue;
+        let bloom_texel = 1. / (targets.size / 2).max(UVec2::ONE).as_vec2();
+        if settings.bloom {
+            let scene_texel = 1. / targets.size.as_vec2();
+            let write = |binding: &ParamsBinding, texel: Vec4| {
+                let params = PostParams {
+                    settings: vec4(BLOOM_THRESHOLD, 0., 0., 0.),
+                    texel,
+                };
+                queue.write_buffer(&binding.buffer, 0, bytemuck::bytes_of(&params));
+            };
+            write(
+                &self.extract_params,
+                vec4(scene_texel.x, scene_texel.y, 0., 0.),
+            );
+            write(
+                &self.blur_params[0],
+                vec4(bloom_texel.x, bloom_texel.y, BLOOM_SPREAD, 0.),
+            );
+            write(
+                &self.blur_params[1],
+                vec4(bloom_texel.x, bloom_texel.y, 0., BLOOM_SPREAD),
+            );
+
+            let [bloom_a, bloom_b] = &targets.bloom;
+            self.fullscreen(
+                encoder,
+                &self.extract_pipeline,
+                &self.extract_params,
+                &[&targets.scene],
+                &bloom_a.texture_view,
+            );
+            self.fullscreen(
+                encoder,
+                &self.blur_pipeline,
+                &self.blur_params[0],
+                &[bloom_a],
+                &bloom_b.texture_view,
+            );
+            self.fullscreen(
+                encoder,
+                &self.blur_pipeline,
+                &self.blur_params[1],
+                &[bloom_b],
+                &bloom_a.texture_view,
+            );
+        }
+
+        let enabled = |enabled: bool, value: f32| if enabled { value } else { 0. };
+        let params = PostParams {
+            settings: vec4(
+                BLOOM_THRESHOLD,
+                enabled(settings.bloom, BLOOM_INTENSITY),
+                enabled(settings.vignette, VIGNETTE_STRENGTH),
+                enabled(settings.color_grading, COLOR_GRADING_STRENGTH),
+            ),
+            texel: Vec4::ZERO,
+        };
+        queue.write_buffer(
+            &self.composite_params.buffer,
+            0,
+            bytemuck::bytes_of(&params),
+        );
+        self.fullscreen(
+            encoder,
+            &self.composite_pipeline,
+            &self.composite_params,
+            &[&targets.scene, &targets.bloom[0], &self.lut],
+            output,
+        );
+    }
+
+    fn fullscreen(
+        &self,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        params: &ParamsBinding,
+        inputs: &[&QuadTexture],
+        output: &TextureView,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("post pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, Some(&params.bind_group), &[]);
+        for (i, input) in inputs.iter().enumerate() {
+            rpass.set_bind_group(i as u32 + 1, Some(&input.bind), &[]);
+        }
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -1,17 +1,25 @@
+use crate::rendering::atlas::{ATLAS_TEXTURES, Atlas, TextureRegion};
 use crate::rendering::framedata::{FrameDataBindGroupLayout, FrameDataBinding};
 use crate::rendering::game_renderer::RenderConfig;
 use crate::rendering::quad_texture::{QuadTexture, QuadTextureBindGroupLayout};
+use crate::rendering::stream_buffer::StreamBuffer;
 use bytemuck::{Pod, Zeroable};
+use glam::{Vec2, Vec4, vec2};
 use image::ImageFormat;
 use std::borrow::Cow;
 use std::mem::offset_of;
+use std::ops::Range;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{
     Buffer, BufferAddress, BufferUsages, ColorTargetState, RenderPass, RenderPipeline,
     VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode,
 };
 
+/// quads covered by the index buffer, larger draws are split into multiple draw calls
 pub const MAX_QUADS_PER_DRAW: u32 = 1024;
+/// initial size of the [`QuadRenderer::vertex_stream`] in bytes, grows as needed
+const VERTEX_STREAM_CAPACITY: BufferAddress = 1 << 16;
+const VERTEX_SIZE: BufferAddress = size_of::<QuadVertex>() as BufferAddress;
 
 #[derive(Debug, Copy, Clone, Zeroable, Pod)]
 #[repr(C)]
@@ -21,28 +29,79 @@ pub struct QuadVertex {
     pub vtx_color: glam::Vec4,
 }
 
+impl QuadVertex {
+    /// The vertices of a quad in Z order, `position` maps the corners of the unit square, which are
+    /// also the tex coords
+    pub fn quad(vtx_color: Vec4, position: impl Fn(Vec2) -> Vec2) -> [Self; 4] {
+        [vec2(0., 0.), vec2(0., 1.), vec2(1., 0.), vec2(1., 1.)].map(|corner| Self {
+            position: position(corner),
+            tex_coord: corner,
+            vtx_color,
+        })
+    }
+
+    /// an axis aligned rect from `pos` to `pos + size`
+    pub fn rect(pos: Vec2, size: Vec2, vtx_color: Vec4) -> [Self; 4] {
+        Self::quad(vtx_color, |corner| corner * size + pos)
+    }
+
+    /// the same vertex with its tex coord mapped into `region`, see [`TextureRegion::uv`]
+    pub fn in_region(self, region: &TextureRegion) -> Self {
+        Self {
+            tex_coord: region.uv(self.tex_coord),
+            ..self
+        }
+    }
+}
+
 pub struct QuadVertexBuffer {
     pub buffer: Buffer,
+    pub offset: BufferAddress,
     len: u32,
 }
 
 impl QuadVertexBuffer {
+    /// A dedicated buffer for vertices that live longer than a frame
+    ///
     /// vertices must be divisible by 4 and the vertices of a quad should be organized in a Z like pattern
     pub fn new(config: &RenderConfig, vertices: &[QuadVertex]) -> Self {
-        assert!(vertices.len() < MAX_QUADS_PER_DRAW as usize);
+        assert_eq!(vertices.len() % 4, 0);
         Self {
             buffer: config.device.create_buffer_init(&BufferInitDescriptor {
                 label: Some("vertices"),
                 contents: bytemuck::cast_slice(vertices),
                 usage: BufferUsages::VERTEX,
             }),
+            offset: 0,
+            len: vertices.len() as u32,
+        }
+    }
+
+    /// Vertices for this frame only, written into the shared [`StreamBuffer`]
+    pub fn stream(stream: &StreamBuffer, vertices: &[QuadVertex]) -> Self {
+        assert_eq!(vertices.len() % 4, 0);
+        let slice = stream.push(bytemuck::cast_slice(vertices));
+        Self {
+            buffer: slice.buffer,
+            offset: slice.offset,
             len: vertices.len() as u32,
         }
     }
 
+    /// the number of vertices, four per quad
     pub fn len(&self) -> u32 {
         self.len
     }
+
+    /// A view of the quads within `range`, sharing the same buffer
+    pub fn quads(&self, range: Range<u32>) -> Self {
+        assert!(range.start <= range.end && range.end * 4 <= self.len);
+        Self {
+            buffer: self.buffer.clone(),
+            offset: self.offset + (range.start * 4) as BufferAddress * VERTEX_SIZE,
+            len: range.len() as u32 * 4,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -50,6 +109,9 @@ pub struct QuadRenderer {
     pub config: RenderConfig,
     pub frame_data_layout: FrameDataBindGroupLayout,
     pub texture_layout: QuadTextureBindGroupLayout,
+    pub atlas: Atlas,
+    /// per frame vertices of all quad renderers
+    pub vertex_stream: StreamBuffer,
     color_pipeline: RenderPipeline,
     texture_pipeline: RenderPipeline,
     masked_pipeline: RenderPipeline,
@@ -57,9 +119,17 @@ pub struct QuadRenderer {
 }
 
 impl QuadRenderer {
-    pub fn load_texture(&self, bytes: &[u8]) -> QuadTexture {
+    /// The region of the png within the shared atlas, or a texture of its own if it is not part of it
+    pub fn load_texture(&self, bytes: &[u8]) -> TextureRegion {
+        if let Some(region) = self.atlas.get(bytes) {
+            return region.clone();
+        }
         let image = image::load_from_memory_with_format(bytes, ImageFormat::Png).unwrap();
-        QuadTexture::upload(&self.config, &self.texture_layout, &image.to_rgba8())
+        TextureRegion::full(QuadTexture::upload(
+            &self.config,
+            &self.texture_layout,
+            &image.to_rgba8(),
+        ))
     }
 
     pub fn new(
@@ -79,7 +149,7 @@ impl QuadRenderer {
             module: &shader,
             entry_point: Some("vs_main"),
             buffers: &[VertexBufferLayout {
-                array_stride: size_of::<QuadVertex>() as u64,
+                array_stride: VERTEX_SIZE,
                 step_mode: VertexStepMode::Vertex,
                 attributes: &[
                     VertexAttribute {
@@ -190,6 +260,15 @@ impl QuadRenderer {
         });
 
         Self {
+            atlas: Atlas::new(config, &texture_layout, &ATLAS_TEXTURES)
+                .expect("embedded atlas textures are valid"),
+            vertex_stream: StreamBuffer::new(
+                config,
+                "vertex stream",
+                BufferUsages::VERTEX,
+                VERTEX_SIZE,
+                VERTEX_STREAM_CAPACITY,
+            ),
             config: config.clone(),
             frame_data_layout,
             texture_layout,
@@ -200,6 +279,12 @@ impl QuadRenderer {
         }
     }
 
+    /// Starts a new frame, reusing the vertex and uniform memory of the last one
+    pub fn begin_frame(&self) {
+        self.vertex_stream.reset();
+        self.frame_data_layout.reset();
+    }
+
     pub fn draw_color(
         &self,
         rpass: &mut RenderPass,
@@ -242,9 +327,45 @@ impl QuadRenderer {
         frame_data: &FrameDataBinding,
         vertices: &QuadVertexBuffer,
     ) {
+        if vertices.len() == 0 {
+            return;
+        }
         rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        rpass.set_vertex_buffer(0, vertices.buffer.slice(..));
-        rpass.set_bind_group(0, Some(&frame_data.0), &[]);
-        rpass.draw_indexed(0..vertices.len() / 4 * 6, 0, 0..1);
+        rpass.set_vertex_buffer(
+            0,
+            vertices.buffer.slice(
+                vertices.offset..vertices.offset + vertices.len() as BufferAddress * VERTEX_SIZE,
+            ),
+        );
+        rpass.set_bind_group(0, Some(&frame_data.bind_group), &[frame_data.offset]);
+        for quads in draw_ranges(vertices.len() / 4) {
+            rpass.draw_indexed(0..quads.len() as u32 * 6, (quads.start * 4) as i32, 0..1);
+        }
+    }
+}
+
+/// splits `quads` into the ranges drawn by a single draw call each
+fn draw_ranges(quads: u32) -> impl Iterator<Item = Range<u32>> {
+    (0..quads)
+        .step_by(MAX_QUADS_PER_DRAW as usize)
+        .map(move |first| first..(first + MAX_QUADS_PER_DRAW).min(quads))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_draws_are_split() {
+        assert_eq!(draw_ranges(0).count(), 0);
+        assert!(draw_ranges(MAX_QUADS_PER_DRAW).eq(std::iter::once(0..MAX_QUADS_PER_DRAW)));
+        assert_eq!(
+            draw_ranges(2 * MAX_QUADS_PER_DRAW + 1).collect::<Vec<_>>(),
+            [
+                0..MAX_QUADS_PER_DRAW,
+                MAX_QUADS_PER_DRAW..2 * MAX_QUADS_PER_DRAW,
+                2 * MAX_QUADS_PER_DRAW..2 * MAX_QUADS_PER_DRAW + 1,
+            ]
+        );
     }
 }
@@ -73,3 +73,96 @@ fn fs_masked(
 	}
 	return color;
 }
+
+
+
+struct PostParams {
+	settings: vec4<f32>,
+	texel: vec4<f32>,
+};
+
+struct FullscreenOutput {
+    @location(0) uv: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> post_params: PostParams;
+
+@group(2)
+@binding(0)
+var bloom_texture: texture_2d<f32>;
+
+@group(2)
+@binding(1)
+var bloom_sampler: sampler;
+
+@group(3)
+@binding(0)
+var lut_texture: texture_2d<f32>;
+
+@group(3)
+@binding(1)
+var lut_sampler: sampler;
+
+const LUT_SIZE: f32 = 16.;
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> FullscreenOutput {
+	var result: FullscreenOutput;
+	let uv = vec2(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+	result.uv = vec2(uv.x, 1. - uv.y);
+	result.position = vec4(uv * 2. - 1., 0., 1.);
+	return result;
+}
+
+@fragment
+fn fs_bloom_extract(vertex: FullscreenOutput) -> @location(0) vec4<f32> {
+	let texel = post_params.texel.xy * 0.5;
+	let color = (textureSample(my_texture, my_sampler, vertex.uv + vec2(-texel.x, -texel.y))
+		+ textureSample(my_texture, my_sampler, vertex.uv + vec2(texel.x, -texel.y))
+		+ textureSample(my_texture, my_sampler, vertex.uv + vec2(-texel.x, texel.y))
+		+ textureSample(my_texture, my_sampler, vertex.uv + vec2(texel.x, texel.y))).rgb * 0.25;
+	return vec4(max(color - vec3(post_params.settings.x), vec3(0.)), 1.);
+}
+
+fn blur_tap(uv: vec2<f32>, offset: vec2<f32>) -> vec3<f32> {
+	return textureSample(my_texture, my_sampler, uv + offset).rgb
+		+ textureSample(my_texture, my_sampler, uv - offset).rgb;
+}
+
+@fragment
+fn fs_blur(vertex: FullscreenOutput) -> @location(0) vec4<f32> {
+	let step = post_params.texel.xy * post_params.texel.zw;
+	let color = textureSample(my_texture, my_sampler, vertex.uv).rgb * 0.227027
+		+ blur_tap(vertex.uv, step) * 0.1945946
+		+ blur_tap(vertex.uv, step * 2.) * 0.1216216
+		+ blur_tap(vertex.uv, step * 3.) * 0.054054
+		+ blur_tap(vertex.uv, step * 4.) * 0.016216;
+	return vec4(color, 1.);
+}
+
+@fragment
+fn fs_composite(vertex: FullscreenOutput) -> @location(0) vec4<f32> {
+	let settings = post_params.settings;
+	var color = textureSample(my_texture, my_sampler, vertex.uv).rgb
+		+ textureSample(bloom_texture, bloom_sampler, vertex.uv).rgb * settings.y;
+	color /= max(max(max(color.r, color.g), color.b), 1.);
+
+	let from_center = (vertex.uv - 0.5) * 2.;
+	color *= max(1. - settings.z * dot(from_center, from_center) * 0.5, 0.);
+
+	// sampled outside of the branch to stay in uniform control flow
+	let blue = color.b * (LUT_SIZE - 1.);
+	let slice = floor(blue);
+	let next = min(slice + 1., LUT_SIZE - 1.);
+	let within = (color.rg * (LUT_SIZE - 1.) + 0.5) / vec2(LUT_SIZE * LUT_SIZE, LUT_SIZE);
+	let graded = mix(
+		textureSample(lut_texture, lut_sampler, within + vec2(slice / LUT_SIZE, 0.)).rgb,
+		textureSample(lut_texture, lut_sampler, within + vec2(next / LUT_SIZE, 0.)).rgb,
+		blue - slice,
+	);
+	color = mix(color, graded, settings.w);
+	return vec4(color, 1.);
+}
@@ -0,0 +1,422 @@
+use crate::rendering::framedata::FrameData;
+use crate::rendering::quad::QuadVertex;
+use glam::{IVec2, UVec2, Vec2, Vec4, Vec4Swizzles, ivec2, vec2, vec4};
+use image::{GrayImage, Rgba, RgbaImage};
+
+/// The pipeline and textures a draw of the [`SoftwareRenderer`] uses, all color textures are sRGB
+#[derive(Debug, Copy, Clone)]
+pub enum SoftwareMaterial<'a> {
+    /// `fs_color`
+    Color,
+    /// `fs_texture`
+    Texture(&'a RgbaImage),
+    /// `fs_masked`, the mask is fetched at the pixel being drawn
+    Masked {
+        texture: &'a RgbaImage,
+        mask: &'a GrayImage,
+    },
+}
+
+/// A CPU implementation of the quad pipelines, matching them closely enough to check rendering
+/// logic without a GPU
+///
+/// Draws take the same [`QuadVertex`] streams and [`FrameData`] as the [`crate::rendering::quad::QuadRenderer`].
+/// Rasterization follows the top-left rule, textures are sampled bilinear clamped to their edge and
+/// blending happens in linear space like it does on an sRGB target.
+#[derive(Debug, Clone)]
+pub struct SoftwareRenderer {
+    pub size: UVec2,
+    /// linear colors, row 0 is the top of the image
+    pixels: Vec<Vec4>,
+    viewport_pos: Vec2,
+    viewport_size: Vec2,
+}
+
+impl SoftwareRenderer {
+    /// a target cleared to `clear`, given in sRGB like pixels of an image
+    pub fn new(size: UVec2, clear: Rgba<u8>) -> Self {
+        Self::from_image(&RgbaImage::from_pixel(size.x, size.y, clear))
+    }
+
+    /// continues drawing onto `image`, like [`wgpu::LoadOp::Load`]
+    pub fn from_image(image: &RgbaImage) -> Self {
+        let size = UVec2::new(image.width(), image.height());
+        Self {
+            size,
+            pixels: image.pixels().map(|p| decode(*p)).collect(),
+            viewport_pos: Vec2::ZERO,
+            viewport_size: size.as_vec2(),
+        }
+    }
+
+    /// like [`wgpu::RenderPass::set_viewport`], in pixels from the top left
+    pub fn set_viewport(&mut self, pos: Vec2, size: Vec2) {
+        self.viewport_pos = pos;
+        self.viewport_size = size;
+    }
+
+    pub fn image(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.size.x, self.size.y, |x, y| {
+            encode(self.pixels[(y * self.size.x + x) as usize])
+        })
+    }
+
+    /// Draws quads of four vertices each, organized in a Z like pattern
+    pub fn draw(
+        &mut self,
+        frame_data: FrameData,
+        material: SoftwareMaterial,
+        vertices: &[QuadVertex],
+    ) {
+        for quad in vertices.chunks_exact(4) {
+            for [a, b, c] in [[0, 1, 2], [2, 1, 3]] {
+                self.draw_triangle(frame_data, material, [quad[a], quad[b], quad[c]]);
+            }
+        }
+    }
+
+    /// vertex shader and viewport transform, to pixels from the top left
+    fn to_screen(&self, frame_data: FrameData, position: Vec2) -> Vec2 {
+        let ndc = position * frame_data.viewport.zw() + frame_data.viewport.xy();
+        self.viewport_pos + vec2(ndc.x + 1., 1. - ndc.y) * 0.5 * self.viewport_size
+    }
+
+    fn draw_triangle(
+        &mut self,
+        frame_data: FrameData,
+        material: SoftwareMaterial,
+        mut vertices: [QuadVertex; 3],
+    ) {
+        let mut screen = vertices.map(|v| self.to_screen(frame_data, v.position));
+        let area = edge(screen[0], screen[1], screen[2]);
+        if area == 0. {
+            return;
+        }
+        // no culling, just make every triangle wind the same way
+        if area < 0. {
+            vertices.swap(1, 2);
+            screen.swap(1, 2);
+        }
+        let area = area.abs();
+
+        let clip_min = self.viewport_pos.max(Vec2::ZERO);
+        let clip_max = (self.viewport_pos + self.viewport_size).min(self.size.as_vec2());
+        let min = screen[0]
+            .min(screen[1])
+            .min(screen[2])
+            .max(clip_min)
+            .floor();
+        let max = screen[0].max(screen[1]).max(screen[2]).min(clip_max).ceil();
+        if min.cmpge(max).any() {
+            return;
+        }
+        let edges = [(1, 2), (2, 0), (0, 1)];
+        for y in min.y as u32..max.y as u32 {
+            for x in min.x as u32..max.x as u32 {
+                let center = vec2(x as f32, y as f32) + 0.5;
+                let weights = edges.map(|(a, b)| edge(screen[a], screen[b], center));
+                let covered = weights
+                    .iter()
+                    .zip(edges)
+                    .all(|(w, (a, b))| *w > 0. || (*w == 0. && is_top_left(screen[b] - screen[a])));
+                if !covered {
+                    continue;
+                }
+                let [b0, b1, b2] = weights.map(|w| w / area);
+                let color = vertices[0].vtx_color * b0
+                    + vertices[1].vtx_color * b1
+                    + vertices[2].vtx_color * b2;
+                let tex_coord = vertices[0].tex_coord * b0
+                    + vertices[1].tex_coord * b1
+                    + vertices[2].tex_coord * b2;
+                if let Some(src) = shade(material, color, tex_coord, x, y) {
+                    let dst = &mut self.pixels[(y * self.size.x + x) as usize];
+                    *dst = blend(src, *dst);
+                }
+            }
+        }
+    }
+}
+
+/// the fragment shaders, `None` if the fragment is discarded
+fn shade(
+    material: SoftwareMaterial,
+    vtx_color: Vec4,
+    tex_coord: Vec2,
+    x: u32,
+    y: u32,
+) -> Option<Vec4> {
+    let color = match material {
+        SoftwareMaterial::Color => return Some(vtx_color),
+        SoftwareMaterial::Texture(texture) => sample(texture, tex_coord) * vtx_color,
+        SoftwareMaterial::Masked { texture, mask } => {
+            let mask = mask
+                .get_pixel_checked(x, y)
+                .map_or(0., |p| p.0[0] as f32 / 255.);
+            if mask < 0.01 {
+                return None;
+            }
+            sample(texture, tex_coord) * vtx_color
+        }
+    };
+    (color.w >= 0.01).then_some(color)
+}
+
+/// `wgpu::BlendState::ALPHA_BLENDING`
+fn blend(src: Vec4, dst: Vec4) -> Vec4 {
+    let rgb = src.xyz() * src.w + dst.xyz() * (1. - src.w);
+    let alpha = src.w + dst.w * (1. - src.w);
+    Vec4::from((rgb, alpha))
+}
+
+/// twice the signed area of the triangle `a`, `b`, `p`
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// whether pixel centers exactly on an edge going along `dir` belong to the triangle, the neighbouring
+/// triangle walks the edge the other way, so exactly one of both draws it
+fn is_top_left(dir: Vec2) -> bool {
+    dir.y > 0. || (dir.y == 0. && dir.x < 0.)
+}
+
+/// bilinear filtered and clamped to the edge, like the quad texture sampler
+fn sample(texture: &RgbaImage, uv: Vec2) -> Vec4 {
+    let size = UVec2::new(texture.width(), texture.height());
+    let max = size.as_ivec2() - 1;
+    let pos = uv * size.as_vec2() - 0.5;
+    let base = pos.floor();
+    let fract = pos - base;
+    let texel = |dx: i32, dy: i32| {
+        let p = (base.as_ivec2() + ivec2(dx, dy)).clamp(IVec2::ZERO, max);
+        decode(*texture.get_pixel(p.x as u32, p.y as u32))
+    };
+    let top = texel(0, 0).lerp(texel(1, 0), fract.x);
+    let bottom = texel(0, 1).lerp(texel(1, 1), fract.x);
+    top.lerp(bottom, fract.y)
+}
+
+fn decode(pixel: Rgba<u8>) -> Vec4 {
+    let [r, g, b, a] = pixel.0.map(|c| c as f32 / 255.);
+    vec4(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+}
+
+fn encode(color: Vec4) -> Rgba<u8> {
+    let color = color.clamp(Vec4::ZERO, Vec4::ONE);
+    let rgb = [color.x, color.y, color.z].map(linear_to_srgb);
+    Rgba([rgb[0], rgb[1], rgb[2], color.w].map(|c| (c * 255.).round() as u8))
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+    use crate::paint::{RevealMask, SplatStamp, load_splat_images};
+    use crate::rendering::camera::Camera;
+    use crate::rendering::framedata::{VIEWPORT_SIZE, screen_viewport};
+    use crate::save::Scaling;
+    use glam::uvec2;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn count(image: &RgbaImage, color: Rgba<u8>) -> usize {
+        image.pixels().filter(|p| **p == color).count()
+    }
+
+    #[test]
+    fn color_quads_cover_exactly_their_pixels() {
+        let mut renderer = SoftwareRenderer::new(UVec2::new(8, 6), BLACK);
+        let frame_data = FrameData {
+            viewport: Vec4::from((Vec2::NEG_ONE, 2. / vec2(8., 6.))),
+        };
+        // the shared diagonal must neither leave a gap nor be blended twice
+        let half_white = vec4(1., 1., 1., 0.5);
+        renderer.draw(
+            frame_data,
+            SoftwareMaterial::Color,
+            &QuadVertex::rect(vec2(2., 0.), vec2(4., 4.), half_white),
+        );
+        let image = renderer.image();
+        let blended = *image.get_pixel(3, 3);
+        assert_ne!(blended, BLACK);
+        assert_eq!(count(&image, blended), 16);
+        assert_eq!(count(&image, BLACK), 8 * 6 - 16);
+        // world y is up, so the quad ends up at the bottom of the image
+        assert_eq!(*image.get_pixel(2, 5), blended);
+        assert_eq!(*image.get_pixel(2, 1), BLACK);
+    }
+
+    #[test]
+    fn texture_discards_transparent_texels_and_tints() {
+        let mut texture = RgbaImage::from_pixel(2, 1, WHITE);
+        texture.put_pixel(1, 0, Rgba([255, 255, 255, 0]));
+        let mut renderer = SoftwareRenderer::new(UVec2::new(40, 1), BLACK);
+        let frame_data = FrameData {
+            viewport: Vec4::from((Vec2::NEG_ONE, 2. / vec2(40., 1.))),
+        };
+        let red = vec4(1., 0., 0., 1.);
+        renderer.draw(
+            frame_data,
+            SoftwareMaterial::Texture(&texture),
+            &QuadVertex::rect(Vec2::ZERO, vec2(40., 1.), red),
+        );
+        let image = renderer.image();
+        assert_eq!(*image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(39, 0), BLACK);
+    }
+
+    #[test]
+    fn masked_only_draws_within_mask() {
+        let texture = RgbaImage::from_pixel(1, 1, WHITE);
+        let mask = GrayImage::from_fn(4, 4, |x, _| image::Luma([if x < 2 { 255 } else { 0 }]));
+        let mut renderer = SoftwareRenderer::new(UVec2::new(4, 4), BLACK);
+        let frame_data = FrameData {
+            viewport: Vec4::from((Vec2::NEG_ONE, 2. / vec2(4., 4.))),
+        };
+        let material = SoftwareMaterial::Masked {
+            texture: &texture,
+            mask: &mask,
+        };
+        renderer.draw(
+            frame_data,
+            material,
+            &QuadVertex::rect(Vec2::ZERO, vec2(4., 4.), Vec4::ONE),
+        );
+        let image = renderer.image();
+        assert_eq!(count(&image, WHITE), 8);
+        assert!((0..4).all(|y| *image.get_pixel(1, y) == WHITE && *image.get_pixel(2, y) == BLACK));
+    }
+
+    #[test]
+    fn camera_letterboxes_ui() {
+        let camera = Camera::new(UVec2::new(1000, 600));
+        let (pos, size) = camera.letterbox();
+        assert_eq!((pos, size), (UVec2::new(100, 0), UVec2::new(800, 600)));
+
+        let mut renderer = SoftwareRenderer::new(camera.surface_size, BLACK);
+        renderer.set_viewport(pos.as_vec2(), size.as_vec2());
+        let frame_data = FrameData {
+            viewport: screen_viewport(),
+        };
+        renderer.draw(
+            frame_data,
+            SoftwareMaterial::Color,
+            &QuadVertex::rect(Vec2::ZERO, VIEWPORT_SIZE, Vec4::ONE),
+        );
+        let image = renderer.image();
+        assert_eq!(count(&image, WHITE), 800 * 600);
+        assert_eq!(*image.get_pixel(99, 300), BLACK);
+        assert_eq!(*image.get_pixel(100, 300), WHITE);
+        assert_eq!(*image.get_pixel(900, 300), BLACK);
+    }
+
+    #[test]
+    fn camera_projects_world_points_onto_their_pixels() {
+        let cameras = [
+            // scale 2 and zoom 2, a world unit covers 4 pixels
+            (uvec2(1600, 1200), Scaling::Integer, 2.),
+            // letterboxed at scale 1, zoomed out so a pixel covers 2 world units
+            (uvec2(1000, 600), Scaling::Free, 0.5),
+        ];
+        let center = vec2(500., 300.);
+        for (surface_size, scaling, zoom) in cameras {
+            let camera = Camera {
+                surface_size,
+                scaling,
+                zoom,
+            };
+            let (pos, size) = camera.letterbox();
+            let pixel = 1. / (camera.scale() * zoom);
+            // where the world point ends up in surface pixels from the top left
+            let project = |point: Vec2| {
+                pos.as_vec2() + size.as_vec2() * 0.5 + (point - center) * vec2(1., -1.) / pixel
+            };
+            for point in [center, center + vec2(64., 32.), center - vec2(100., 20.)] {
+                let mut renderer = SoftwareRenderer::new(surface_size, BLACK);
+                renderer.set_viewport(pos.as_vec2(), size.as_vec2());
+                let frame_data = FrameData {
+                    viewport: camera.viewport_at(center),
+                };
+                renderer.draw(
+                    frame_data,
+                    SoftwareMaterial::Color,
+                    &QuadVertex::rect(point, Vec2::splat(pixel), Vec4::ONE),
+                );
+                let image = renderer.image();
+                // the quad spans one pixel up from the point, as world y is up
+                let expected = project(point + vec2(0., pixel)).as_uvec2();
+                assert_eq!(count(&image, WHITE), 1, "{point} at zoom {zoom}");
+                assert_eq!(*image.get_pixel(expected.x, expected.y), WHITE);
+            }
+        }
+    }
+
+    #[test]
+    fn masked_stamps_match_the_reveal_mask() {
+        let level =
+            Level::from_ascii(&["        ", "  ##    ", " #### # ", "##  ####", "########"]);
+        let shapes = load_splat_images().unwrap();
+        let stamps = [
+            (vec2(20., 20.), 12., 0., 0),
+            (vec2(41.5, 27.), 9., 0.7, 1),
+            (vec2(60., 10.), 15., 2.1, 2),
+            (vec2(18., 36.), 10., 4., 0),
+        ];
+        for (pos, size, rotation, texture) in stamps {
+            let stamp = SplatStamp {
+                pos,
+                size,
+                rotation,
+                texture,
+                color: vec4(0., 1., 0., 1.),
+            };
+            let mut paint = RevealMask::new(&level);
+            paint.stamp(&level, &stamp);
+
+            // the level texture pass, its rows being world y like the reveal mask
+            let mut renderer = SoftwareRenderer::new(level.size, Rgba([0, 0, 0, 0]));
+            let frame_data = FrameData {
+                viewport: Vec4::from((Vec2::NEG_ONE, 2. / level.size.as_vec2())),
+            };
+            let corners = stamp.corners(level.size.y);
+            let vertices = QuadVertex::quad(stamp.color, |corner| {
+                corners[(corner.x * 2. + corner.y) as usize]
+            });
+            renderer.draw(
+                frame_data,
+                SoftwareMaterial::Masked {
+                    texture: &shapes[texture],
+                    mask: &level.collision_map,
+                },
+                &vertices,
+            );
+            let image = renderer.image();
+
+            assert!(paint.revealed_count() > 0);
+            for (x, y, pixel) in image.enumerate_pixels() {
+                assert_eq!(
+                    pixel.0[3] > 0,
+                    paint.is_revealed(ivec2(x as i32, y as i32)),
+                    "({x}, {y}) of {stamp:?}"
+                );
+            }
+        }
+    }
+}
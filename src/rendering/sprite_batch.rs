@@ -0,0 +1,164 @@
+use crate::rendering::framedata::FrameDataBinding;
+use crate::rendering::quad::{QuadRenderer, QuadVertex, QuadVertexBuffer};
+use crate::rendering::quad_texture::QuadTexture;
+use std::ops::Range;
+use wgpu::{BindGroup, RenderPass};
+
+/// The pipeline and textures a sprite is drawn with
+#[derive(Debug, Copy, Clone)]
+pub enum SpriteMaterial<'a> {
+    Color,
+    Texture(&'a QuadTexture),
+    Masked {
+        texture: &'a QuadTexture,
+        mask: &'a QuadTexture,
+    },
+}
+
+impl<'a> SpriteMaterial<'a> {
+    /// sprites with equal keys can be drawn with a single draw call, textures sharing a bind group
+    /// like [`crate::rendering::atlas::Atlas`] regions compare equal
+    fn key(&self) -> (u8, Option<&'a BindGroup>, Option<&'a BindGroup>) {
+        match *self {
+            SpriteMaterial::Color => (0, None, None),
+            SpriteMaterial::Texture(texture) => (1, Some(&texture.bind), None),
+            SpriteMaterial::Masked { texture, mask } => (2, Some(&texture.bind), Some(&mask.bind)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Sprite<'a> {
+    layer: i32,
+    material: SpriteMaterial<'a>,
+    vertices: [QuadVertex; 4],
+}
+
+/// Collects quads of any material and draws them with as few draw calls as possible
+///
+/// Sprites are drawn by ascending layer. Within a layer they are grouped by material, so overlapping
+/// sprites that need a specific order must be put on different layers.
+#[derive(Debug, Clone, Default)]
+pub struct SpriteBatch<'a> {
+    sprites: Vec<Sprite<'a>>,
+}
+
+impl<'a> SpriteBatch<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the vertices of the quad should be organized in a Z like pattern
+    pub fn push(&mut self, layer: i32, material: SpriteMaterial<'a>, vertices: [QuadVertex; 4]) {
+        self.sprites.push(Sprite {
+            layer,
+            material,
+            vertices,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    pub fn draw(
+        mut self,
+        quad: &QuadRenderer,
+        rpass: &mut RenderPass,
+        frame_data: &FrameDataBinding,
+    ) {
+        if self.sprites.is_empty() {
+            return;
+        }
+        let runs = self.runs();
+        let vertices = QuadVertexBuffer::stream(
+            &quad.vertex_stream,
+            &self
+                .sprites
+                .iter()
+                .flat_map(|sprite| sprite.vertices)
+                .collect::<Vec<_>>(),
+        );
+
+        for (material, quads) in runs {
+            let vertices = vertices.quads(quads);
+            match material {
+                SpriteMaterial::Color => quad.draw_color(rpass, frame_data, &vertices),
+                SpriteMaterial::Texture(texture) => {
+                    quad.draw_texture(rpass, frame_data, &vertices, texture)
+                }
+                SpriteMaterial::Masked { texture, mask } => {
+                    quad.draw_masked(rpass, frame_data, &vertices, texture, mask)
+                }
+            }
+        }
+    }
+
+    /// Sorts the sprites into draw order, returning the material and quad range of each draw
+    fn runs(&mut self) -> Vec<(SpriteMaterial<'a>, Range<u32>)> {
+        // stable, so sprites of the same layer and material keep their order
+        self.sprites
+            .sort_by_key(|sprite| (sprite.layer, sprite.material.key()));
+        let mut start = 0;
+        self.sprites
+            .chunk_by(|a, b| a.layer == b.layer && a.material.key() == b.material.key())
+            .map(|run| {
+                let end = start + run.len() as u32;
+                let range = start..end;
+                start = end;
+                (run[0].material, range)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::quad::MAX_QUADS_PER_DRAW;
+    use glam::{Vec2, Vec4};
+
+    fn quad(x: f32) -> [QuadVertex; 4] {
+        [QuadVertex {
+            position: Vec2::splat(x),
+            tex_coord: Vec2::ZERO,
+            vtx_color: Vec4::ONE,
+        }; 4]
+    }
+
+    #[test]
+    fn runs_group_by_layer_keeping_order() {
+        let mut batch = SpriteBatch::new();
+        batch.push(1, SpriteMaterial::Color, quad(0.));
+        batch.push(0, SpriteMaterial::Color, quad(1.));
+        batch.push(1, SpriteMaterial::Color, quad(2.));
+        batch.push(0, SpriteMaterial::Color, quad(3.));
+        batch.push(2, SpriteMaterial::Color, quad(4.));
+        let runs: Vec<_> = batch.runs().into_iter().map(|(_, quads)| quads).collect();
+        assert_eq!(runs, [0..2, 2..4, 4..5]);
+        let order: Vec<_> = batch
+            .sprites
+            .iter()
+            .map(|sprite| sprite.vertices[0].position.x)
+            .collect();
+        assert_eq!(order, [1., 3., 0., 2., 4.]);
+    }
+
+    #[test]
+    fn large_runs_stay_one_run() {
+        let mut batch = SpriteBatch::new();
+        let count = 2 * MAX_QUADS_PER_DRAW + 1;
+        for i in 0..count {
+            batch.push(0, SpriteMaterial::Color, quad(i as f32));
+        }
+        assert_eq!(batch.len(), count as usize);
+        let runs = batch.runs();
+        // the quad renderer splits the draw at MAX_QUADS_PER_DRAW
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].1, 0..count);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::rendering::game_renderer::RenderConfig;
+use std::sync::{Arc, Mutex};
+use wgpu::{Buffer, BufferAddress, BufferDescriptor, BufferUsages};
+
+/// A part of a [`StreamBuffer`] written this frame
+#[derive(Debug, Clone)]
+pub struct StreamSlice {
+    pub buffer: Buffer,
+    pub offset: BufferAddress,
+    /// changes whenever the [`StreamBuffer`] had to grow and switched to a new buffer
+    pub generation: u64,
+}
+
+/// A GPU buffer that is sub-allocated linearly and reset every frame, so steady state rendering
+/// does not allocate on the device at all
+///
+/// Writes go through [`wgpu::Queue::write_buffer`], which is ordered with all previous submissions,
+/// so it is safe to overwrite the previous frame's data after a [`Self::reset`]. Clones share the same
+/// buffer.
+#[derive(Debug, Clone)]
+pub struct StreamBuffer(Arc<Mutex<StreamState>>);
+
+#[derive(Debug)]
+struct StreamState {
+    config: RenderConfig,
+    label: &'static str,
+    usage: BufferUsages,
+    alignment: BufferAddress,
+    buffer: Buffer,
+    offset: BufferAddress,
+    generation: u64,
+}
+
+impl StreamBuffer {
+    pub fn new(
+        config: &RenderConfig,
+        label: &'static str,
+        usage: BufferUsages,
+        alignment: BufferAddress,
+        capacity: BufferAddress,
+    ) -> Self {
+        let usage = usage | BufferUsages::COPY_DST;
+        Self(Arc::new(Mutex::new(StreamState {
+            buffer: create_buffer(config, label, usage, capacity),
+            config: config.clone(),
+            label,
+            usage,
+            alignment,
+            offset: 0,
+            generation: 0,
+        })))
+    }
+
+    /// Copies `bytes` into the buffer, growing it if this frame has used it up
+    pub fn push(&self, bytes: &[u8]) -> StreamSlice {
+        let mut state = self.0.lock().unwrap();
+        let offset = state.offset.next_multiple_of(state.alignment);
+        let end = offset + bytes.len() as BufferAddress;
+        let offset = if end > state.buffer.size() {
+            let capacity = (state.buffer.size() * 2).max(bytes.len() as BufferAddress);
+            state.buffer = create_buffer(&state.config, state.label, state.usage, capacity);
+            state.generation += 1;
+            0
+        } else {
+            offset
+        };
+        state.offset = offset + bytes.len() as BufferAddress;
+        state
+            .config
+            .queue
+            .write_buffer(&state.buffer, offset, bytes);
+        StreamSlice {
+            buffer: state.buffer.clone(),
+            offset,
+            generation: state.generation,
+        }
+    }
+
+    /// Starts a new frame, reusing the memory of the last one
+    pub fn reset(&self) {
+        self.0.lock().unwrap().offset = 0;
+    }
+}
+
+fn create_buffer(
+    config: &RenderConfig,
+    label: &'static str,
+    usage: BufferUsages,
+    size: BufferAddress,
+) -> Buffer {
+    config.device.create_buffer(&BufferDescriptor {
+        label: Some(label),
+        size,
+        usage,
+        mapped_at_creation: false,
+    })
+}
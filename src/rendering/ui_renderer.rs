@@ -0,0 +1,117 @@
+use crate::level::Level;
+use crate::rendering::framedata::{FrameData, FrameDataBinding, VIEWPORT_SIZE, screen_viewport};
+use crate::rendering::quad::{QuadRenderer, QuadVertex, QuadVertexBuffer};
+use crate::rendering::quad_texture::QuadTexture;
+use crate::ui::{GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, Overlay, THUMBNAIL_SIZE, glyph};
+use glam::{Vec2, Vec4, vec2, vec4};
+use std::sync::Arc;
+use wgpu::RenderPass;
+
+/// thumbnails are generated at a higher resolution than they are drawn at, to stay crisp
+pub const THUMBNAIL_OVERSAMPLING: f32 = 2.;
+
+/// Draws the [`Overlay`] onto the post processed output, so it needs a [`QuadRenderer`] of that format
+pub struct UiRenderer {
+    quad: QuadRenderer,
+    /// indexed by level
+    thumbnails: Vec<QuadTexture>,
+}
+
+impl UiRenderer {
+    pub fn new(quad: QuadRenderer) -> Self {
+        Self {
+            quad,
+            thumbnails: Vec::new(),
+        }
+    }
+
+    /// Must be called at the start of every frame, before anything is drawn
+    pub fn begin_frame(&self) {
+        self.quad.begin_frame();
+    }
+
+    pub fn load_thumbnails(&mut self, levels: &[Arc<Level>]) {
+        let max_size = (THUMBNAIL_SIZE * THUMBNAIL_OVERSAMPLING).as_uvec2();
+        self.thumbnails = levels
+            .iter()
+            .map(|level| {
+                QuadTexture::upload(
+                    &self.quad.config,
+                    &self.quad.texture_layout,
+                    &level.thumbnail(max_size),
+                )
+            })
+            .collect();
+    }
+
+    pub fn draw(&self, rpass: &mut RenderPass, overlay: &Overlay) {
+        let frame_data = self.quad.frame_data_layout.create_bind_group(FrameData {
+            viewport: screen_viewport(),
+        });
+
+        let mut background = Vec::new();
+        if overlay.dim > 0. {
+            push_rect(
+                &mut background,
+                Vec2::ZERO,
+                VIEWPORT_SIZE,
+                vec4(0., 0., 0., overlay.dim),
+            );
+        }
+        for rect in &overlay.rects {
+            push_rect(&mut background, rect.pos, rect.size, rect.color);
+        }
+        self.draw_color(rpass, &frame_data, &background);
+
+        for thumbnail in &overlay.thumbnails {
+            if let Some(texture) = self.thumbnails.get(thumbnail.level_idx) {
+                let mut vertices = Vec::new();
+                push_rect(&mut vertices, thumbnail.pos, thumbnail.size, thumbnail.tint);
+                self.quad.draw_texture(
+                    rpass,
+                    &frame_data,
+                    &QuadVertexBuffer::stream(&self.quad.vertex_stream, &vertices),
+                    texture,
+                );
+            }
+        }
+
+        let mut text = Vec::new();
+        for line in &overlay.lines {
+            for (i, c) in line.text.chars().enumerate() {
+                let origin = line.pos + vec2((i as u32 * GLYPH_ADVANCE) as f32 * line.scale, 0.);
+                for (row, bits) in glyph(c).into_iter().enumerate() {
+                    for column in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                            let pixel = vec2(column as f32, (GLYPH_HEIGHT - 1) as f32 - row as f32);
+                            push_rect(
+                                &mut text,
+                                origin + pixel * line.scale,
+                                Vec2::splat(line.scale),
+                                line.color,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        self.draw_color(rpass, &frame_data, &text);
+    }
+
+    fn draw_color(
+        &self,
+        rpass: &mut RenderPass,
+        frame_data: &FrameDataBinding,
+        vertices: &[QuadVertex],
+    ) {
+        self.quad.draw_color(
+            rpass,
+            frame_data,
+            &QuadVertexBuffer::stream(&self.quad.vertex_stream, vertices),
+        );
+    }
+}
+
+fn push_rect(vertices: &mut Vec<QuadVertex>, pos: Vec2, size: Vec2, vtx_color: Vec4) {
+    vertices.extend(QuadVertex::rect(pos, size, vtx_color));
+}
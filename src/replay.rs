@@ -0,0 +1,296 @@
+use crate::entity::assists::Assists;
+use crate::entity::input::TickInput;
+use crate::level::Level;
+use crate::save::SaveStore;
+use anyhow::{bail, ensure};
+use std::io::{Read, Write};
+
+const MAGIC: [u8; 4] = *b"CBRP";
+pub const REPLAY_VERSION: u16 = 2;
+pub const REPLAY_EXTENSION: &str = "cbreplay";
+/// where the shipped solutions of the levels live, named like [`Replay::file_name`]
+pub const SOLUTIONS_DIR: &str = "replays";
+/// the most ticks [`Replay::read`] reserves memory for up front, as the header can't be trusted
+const MAX_RESERVED_TICKS: usize = 1 << 16;
+
+/// A per-tick input log of a single level attempt, starting from a freshly loaded level
+///
+/// The file format is a header of magic, version, level index, level hash, assist bits, game speed
+/// and tick count, all little endian, followed by the inputs run-length encoded as
+/// `(input bits: u8, run length: u16)` pairs. Version 1 lacks the assists.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Replay {
+    pub level_idx: u32,
+    pub level_hash: u64,
+    /// the assists the attempt was played with, without the purely visual ones
+    pub assists: Assists,
+    pub inputs: Vec<TickInput>,
+}
+
+impl Replay {
+    pub fn new(level_idx: usize, level: &Level, assists: Assists) -> Self {
+        Self {
+            level_idx: level_idx as u32,
+            level_hash: level.hash,
+            assists: assists.simulated(),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, input: TickInput) {
+        self.inputs.push(input);
+    }
+
+    pub fn matches(&self, level_idx: usize, level: &Level) -> bool {
+        self.level_idx as usize == level_idx && self.level_hash == level.hash
+    }
+
+    pub fn file_name(level_idx: usize) -> String {
+        format!("replay_lvl{:02}.{REPLAY_EXTENSION}", level_idx + 1)
+    }
+
+    pub fn write(&self, mut w: impl Write) -> anyhow::Result<()> {
+        w.write_all(&MAGIC)?;
+        w.write_all(&REPLAY_VERSION.to_le_bytes())?;
+        w.write_all(&self.level_idx.to_le_bytes())?;
+        w.write_all(&self.level_hash.to_le_bytes())?;
+        w.write_all(&[self.assists.to_bits()])?;
+        w.write_all(&self.assists.game_speed.to_le_bytes())?;
+        w.write_all(&(self.inputs.len() as u32).to_le_bytes())?;
+
+        let mut inputs = self.inputs.iter().map(|input| input.to_bits()).peekable();
+        while let Some(bits) = inputs.next() {
+            let mut run = 1u16;
+            while run < u16::MAX && inputs.next_if_eq(&bits).is_some() {
+                run += 1;
+            }
+            w.write_all(&[bits])?;
+            w.write_all(&run.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read(mut r: impl Read) -> anyhow::Result<Self> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        ensure!(magic == MAGIC, "not a replay file");
+        let version = u16::from_le_bytes(read_array(&mut r)?);
+        if version == 0 || version > REPLAY_VERSION {
+            bail!("unsupported replay version {version}, expected at most {REPLAY_VERSION}");
+        }
+        let level_idx = u32::from_le_bytes(read_array(&mut r)?);
+        let level_hash = u64::from_le_bytes(read_array(&mut r)?);
+        let assists = if version >= 2 {
+            let [bits] = read_array(&mut r)?;
+            let game_speed = f32::from_le_bytes(read_array(&mut r)?);
+            ensure!(game_speed > 0., "invalid game speed {game_speed}");
+            Assists::from_bits(bits, game_speed)
+        } else {
+            Assists::default()
+        };
+        let ticks = u32::from_le_bytes(read_array(&mut r)?) as usize;
+
+        let mut inputs = Vec::with_capacity(ticks.min(MAX_RESERVED_TICKS));
+        while inputs.len() < ticks {
+            let [bits] = read_array(&mut r)?;
+            let run = u16::from_le_bytes(read_array(&mut r)?);
+            inputs.extend((0..run).map(|_| TickInput::from_bits(bits)));
+        }
+        ensure!(inputs.len() == ticks, "replay runs exceed the tick count");
+        Ok(Self {
+            level_idx,
+            level_hash,
+            assists,
+            inputs,
+        })
+    }
+
+    /// Stores this replay in `store` as the saved replay of its level
+    pub fn store(&self, store: &mut dyn SaveStore) -> anyhow::Result<()> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        store.store(&Self::file_name(self.level_idx as usize), &bytes)
+    }
+
+    /// The replay saved with [`Self::store`] for the given level, if any
+    pub fn load_stored(store: &dyn SaveStore, level_idx: usize) -> anyhow::Result<Option<Self>> {
+        store
+            .load(&Self::file_name(level_idx))?
+            .map(|bytes| Self::read(bytes.as_slice()))
+            .transpose()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        Self::read(std::fs::read(path)?.as_slice())
+    }
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> anyhow::Result<[u8; N]> {
+    let mut array = [0; N];
+    r.read_exact(&mut array)?;
+    Ok(array)
+}
+
+/// Feeds the inputs of a [`Replay`] back tick by tick
+#[derive(Debug, Clone)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub tick: usize,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, tick: 0 }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.inputs.len()
+    }
+
+    /// the next recorded input, or `None` once the replay has run out
+    pub fn next_input(&mut self) -> Option<TickInput> {
+        let input = self.replay.inputs.get(self.tick).copied()?;
+        self.tick += 1;
+        Some(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::game::Game;
+    use crate::save::MemorySaveStore;
+
+    fn replay(inputs: Vec<TickInput>) -> Replay {
+        Replay {
+            level_idx: 3,
+            level_hash: 0x0123_4567_89ab_cdef,
+            assists: Assists {
+                game_speed: 0.5,
+                invincible: true,
+                ..Assists::default()
+            },
+            inputs,
+        }
+    }
+
+    fn round_trip(replay: &Replay) -> Replay {
+        let mut bytes = Vec::new();
+        replay.write(&mut bytes).unwrap();
+        Replay::read(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn write_read_round_trip() {
+        let inputs = (0..16u8)
+            .flat_map(|bits| (0..bits as usize * 3).map(move |_| TickInput::from_bits(bits)))
+            .collect();
+        let replay = replay(inputs);
+        assert_eq!(round_trip(&replay), replay);
+        assert_eq!(
+            round_trip(&self::replay(Vec::new())),
+            self::replay(Vec::new())
+        );
+    }
+
+    #[test]
+    fn store_round_trip() {
+        let mut store = MemorySaveStore::default();
+        assert!(Replay::load_stored(&store, 3).unwrap().is_none());
+        let mut replay = replay(vec![TickInput::default(); 4]);
+        replay.level_idx = 3;
+        replay.store(&mut store).unwrap();
+        assert_eq!(Replay::load_stored(&store, 3).unwrap(), Some(replay));
+    }
+
+    #[test]
+    fn runs_longer_than_u16() {
+        let right = TickInput {
+            right: true,
+            ..TickInput::default()
+        };
+        let mut inputs = vec![right; u16::MAX as usize * 2 + 7];
+        inputs.push(TickInput::default());
+        let replay = replay(inputs);
+
+        let mut bytes = Vec::new();
+        replay.write(&mut bytes).unwrap();
+        // header, then three runs of the right input and one of the default one
+        assert_eq!(bytes.len(), 4 + 2 + 4 + 8 + 1 + 4 + 4 + 4 * 3);
+        assert_eq!(Replay::read(bytes.as_slice()).unwrap(), replay);
+    }
+
+    #[test]
+    fn read_version_1() {
+        let mut bytes = Vec::new();
+        bytes.extend(MAGIC);
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend(42u64.to_le_bytes());
+        bytes.extend(5u32.to_le_bytes());
+        let right_jump = TickInput {
+            right: true,
+            jump: true,
+            ..TickInput::default()
+        };
+        bytes.extend([right_jump.to_bits()]);
+        bytes.extend(2u16.to_le_bytes());
+        bytes.extend([0]);
+        bytes.extend(3u16.to_le_bytes());
+
+        let replay = Replay::read(bytes.as_slice()).unwrap();
+        assert_eq!(replay.level_idx, 2);
+        assert_eq!(replay.level_hash, 42);
+        assert_eq!(replay.assists, Assists::default());
+        assert_eq!(replay.inputs.len(), 5);
+        assert_eq!(replay.inputs[1], right_jump);
+        assert_eq!(replay.inputs[2], TickInput::default());
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let mut bytes = Vec::new();
+        replay(vec![TickInput::default(); 4])
+            .write(&mut bytes)
+            .unwrap();
+
+        let mut version = bytes.clone();
+        version[4..6].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+        assert!(Replay::read(version.as_slice()).is_err());
+
+        // claims more ticks than there are runs
+        let mut ticks = bytes.clone();
+        ticks[23..27].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Replay::read(ticks.as_slice()).is_err());
+
+        assert!(Replay::read(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Replay::read(&b"CBGH"[..]).is_err());
+    }
+
+    #[test]
+    fn solutions_complete_their_level() {
+        let levels = Level::load_file_tree().unwrap();
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(SOLUTIONS_DIR);
+        for (level_idx, level) in levels.iter().enumerate() {
+            let path = dir.join(Replay::file_name(level_idx));
+            assert!(path.exists(), "level {} has no solution", level_idx + 1);
+            let replay = Replay::load(&path).unwrap();
+            assert!(replay.matches(level_idx, level), "{path:?} is outdated");
+            let mut game = Game::new(level.clone(), replay.assists);
+            let completed = replay
+                .inputs
+                .iter()
+                .any(|input| game.tick(*input).level_complete());
+            assert!(completed, "{path:?} doesn't complete its level");
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use crate::entity::assists::Assists;
+use crate::speedrun::PersonalBests;
+use crate::stats::Stats;
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
+
+/// Persistent key-value storage of binary blobs
+pub trait SaveStore: Debug {
+    fn load(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    fn store(&mut self, key: &str, bytes: &[u8]) -> anyhow::Result<()>;
+}
+
+/// Stores every key as a file within a directory
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FsSaveStore {
+    pub dir: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FsSaveStore {
+    pub const DEFAULT_DIR: &str = "save";
+
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SaveStore for FsSaveStore {
+    fn load(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match std::fs::read(self.dir.join(key)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn store(&mut self, key: &str, bytes: &[u8]) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join(key), bytes)?;
+        Ok(())
+    }
+}
+
+/// Stores every key hex encoded in the browser's `localStorage`
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone)]
+pub struct WebSaveStore {
+    storage: web_sys::Storage,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl WebSaveStore {
+    const PREFIX: &str = "colorbubble/";
+
+    pub fn new() -> anyhow::Result<Self> {
+        let storage = web_sys::window()
+            .context("no window")?
+            .local_storage()
+            .ok()
+            .flatten()
+            .context("no local storage")?;
+        Ok(Self { storage })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl SaveStore for WebSaveStore {
+    fn load(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let value = self
+            .storage
+            .get_item(&format!("{}{key}", Self::PREFIX))
+            .ok()
+            .context("failed to read local storage")?;
+        value
+            .map(|hex| {
+                (0..hex.len())
+                    .step_by(2)
+                    .map(|i| {
+                        Ok(u8::from_str_radix(
+                            hex.get(i..i + 2).context("odd hex")?,
+                            16,
+                        )?)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .transpose()
+    }
+
+    fn store(&mut self, key: &str, bytes: &[u8]) -> anyhow::Result<()> {
+        let hex = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        self.storage
+            .set_item(&format!("{}{key}", Self::PREFIX), &hex)
+            .ok()
+            .context("failed to write local storage")
+    }
+}
+
+/// Keeps everything in memory, for tests and headless runs
+#[derive(Debug, Clone, Default)]
+pub struct MemorySaveStore {
+    pub entries: HashMap<String, Vec<u8>>,
+}
+
+impl SaveStore for MemorySaveStore {
+    fn load(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn store(&mut self, key: &str, bytes: &[u8]) -> anyhow::Result<()> {
+        self.entries.insert(key.to_string(), bytes.to_vec());
+        Ok(())
+    }
+}
+
+/// The persistent store of the current platform
+pub fn platform_store() -> Box<dyn SaveStore> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(FsSaveStore::new(FsSaveStore::DEFAULT_DIR));
+    #[cfg(target_arch = "wasm32")]
+    return match WebSaveStore::new() {
+        Ok(store) => Box::new(store),
+        Err(_) => Box::new(MemorySaveStore::default()),
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub show_ghost: bool,
+    pub show_timer: bool,
+    pub assists: Assists,
+    pub zoom: f32,
+    pub scaling: Scaling,
+    pub post: PostSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            show_ghost: true,
+            show_timer: true,
+            assists: Assists::default(),
+            zoom: 1.,
+            scaling: Scaling::default(),
+            post: PostSettings::default(),
+        }
+    }
+}
+
+/// How [`crate::rendering::framedata::VIEWPORT_SIZE`] is scaled up to fill the surface
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scaling {
+    /// as large as fits
+    #[default]
+    Free,
+    /// the largest whole multiple that fits, for crisp pixels
+    Integer,
+}
+
+impl Scaling {
+    pub fn toggle(self) -> Self {
+        match self {
+            Scaling::Free => Scaling::Integer,
+            Scaling::Integer => Scaling::Free,
+        }
+    }
+}
+
+/// Which post effects are enabled, disabling them helps low-end devices
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostSettings {
+    pub bloom: bool,
+    pub vignette: bool,
+    pub color_grading: bool,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            bloom: true,
+            vignette: true,
+            color_grading: true,
+        }
+    }
+}
+
+pub const SAVE_KEY: &str = "progress.json";
+/// where an unreadable save is kept, so that starting over doesn't lose it for good
+pub const CORRUPT_SAVE_KEY: &str = "progress.corrupt.json";
+pub const SAVE_VERSION: u32 = 1;
+
+/// Migrates the json of version `i + 1` to version `i + 2`
+const MIGRATIONS: &[fn(&mut Value)] = &[];
+const _: () = assert!(MIGRATIONS.len() as u32 + 1 == SAVE_VERSION);
+
+/// All progress of a player, stored as versioned json
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub version: u32,
+    /// the number of levels that may be played, always at least the first one
+    pub unlocked_levels: usize,
+    /// the level to continue from
+    pub current_level: usize,
+    pub bests: PersonalBests,
+    pub settings: Settings,
+    pub stats: Stats,
+    /// ids of the unlocked [`crate::achievements::ACHIEVEMENTS`]
+    pub achievements: BTreeSet<String>,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            unlocked_levels: 1,
+            current_level: 0,
+            bests: PersonalBests::default(),
+            settings: Settings::default(),
+            stats: Stats::default(),
+            achievements: BTreeSet::new(),
+        }
+    }
+}
+
+impl SaveData {
+    pub fn load(store: &dyn SaveStore) -> anyhow::Result<Self> {
+        match store.load(SAVE_KEY)? {
+            Some(bytes) => Self::from_json(&bytes),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Loads the save data, starting over if it can't be read after backing it up to
+    /// [`CORRUPT_SAVE_KEY`]
+    pub fn load_or_default(store: &mut dyn SaveStore) -> Self {
+        Self::load(store).unwrap_or_else(|err| {
+            eprintln!("failed to load save data, starting over: {err:#}");
+            let backup = store
+                .load(SAVE_KEY)
+                .and_then(|bytes| store.store(CORRUPT_SAVE_KEY, &bytes.unwrap_or_default()));
+            if let Err(err) = backup {
+                eprintln!("failed to back up save data: {err:#}");
+            }
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, store: &mut dyn SaveStore) -> anyhow::Result<()> {
+        store.store(SAVE_KEY, &serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Parses a save of any older version, migrating it to [`SAVE_VERSION`]
+    pub fn from_json(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut value = serde_json::from_slice::<Value>(bytes).context("corrupt save data")?;
+        let version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .context("save data has no version")? as u32;
+        if version == 0 || version > SAVE_VERSION {
+            bail!("unsupported save version {version}, expected at most {SAVE_VERSION}");
+        }
+        for migration in &MIGRATIONS[version as usize - 1..] {
+            migration(&mut value);
+        }
+        value["version"] = SAVE_VERSION.into();
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn unlock_level(&mut self, level_idx: usize) {
+        self.unlocked_levels = self.unlocked_levels.max(level_idx + 1);
+    }
+
+    pub fn is_unlocked(&self, level_idx: usize) -> bool {
+        level_idx < self.unlocked_levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_load_round_trip() {
+        let mut store = MemorySaveStore::default();
+        assert_eq!(SaveData::load(&store).unwrap(), SaveData::default());
+
+        let mut save = SaveData::default();
+        save.unlock_level(4);
+        save.current_level = 3;
+        save.bests.levels = vec![Some(120), None, Some(300)];
+        save.settings.zoom = 2.;
+        save.settings.assists.invincible = true;
+        save.achievements.insert("finish_level".to_string());
+        save.save(&mut store).unwrap();
+        assert_eq!(SaveData::load(&store).unwrap(), save);
+    }
+
+    #[test]
+    fn missing_fields_are_defaulted() {
+        let save = SaveData::from_json(br#"{"version": 1, "unlocked_levels": 3}"#).unwrap();
+        assert_eq!(
+            save,
+            SaveData {
+                unlocked_levels: 3,
+                ..SaveData::default()
+            }
+        );
+        assert!(save.is_unlocked(2));
+        assert!(!save.is_unlocked(3));
+    }
+
+    #[test]
+    fn reject_unsupported_versions() {
+        for json in [
+            format!(r#"{{"version": {}}}"#, SAVE_VERSION + 1),
+            r#"{"version": 0}"#.to_string(),
+            r#"{"unlocked_levels": 3}"#.to_string(),
+            "not json".to_string(),
+        ] {
+            assert!(SaveData::from_json(json.as_bytes()).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn corrupt_save_is_backed_up() {
+        let mut store = MemorySaveStore::default();
+        store.store(SAVE_KEY, b"{ truncated").unwrap();
+        assert_eq!(SaveData::load_or_default(&mut store), SaveData::default());
+        assert_eq!(
+            store.load(CORRUPT_SAVE_KEY).unwrap().as_deref(),
+            Some(&b"{ truncated"[..])
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use crate::entity::game::Game;
+
+pub const MAX_STARS: u32 = 3;
+/// fraction of the level's solid pixels that have to be painted for a star
+pub const COVERAGE_FOR_STAR: f32 = 0.25;
+/// the most bubbles a flawless run may use for a star
+pub const BUBBLES_FOR_STAR: u32 = 20;
+
+/// How well a level was completed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LevelScore {
+    /// painted fraction of the solid pixels of the level's `collision_map`, in `0..=1`
+    pub coverage: f32,
+    pub bubbles_used: u32,
+    pub deaths: u32,
+    pub orbs_collected: usize,
+    pub orbs_total: usize,
+}
+
+impl LevelScore {
+    pub fn new(game: &Game) -> Self {
+        Self {
+            coverage: game.paint.coverage(),
+            bubbles_used: game.bubbles_used,
+            deaths: game.deaths,
+            orbs_collected: game.orbs.iter().filter(|orb| orb.collected).count(),
+            orbs_total: game.orbs.len(),
+        }
+    }
+
+    /// One star for finishing, one for painting [`COVERAGE_FOR_STAR`] of the level and one for
+    /// finishing without dying and with at most [`BUBBLES_FOR_STAR`] bubbles.
+    pub fn stars(&self) -> u32 {
+        let mut stars = 1;
+        if self.coverage >= COVERAGE_FOR_STAR {
+            stars += 1;
+        }
+        if self.deaths == 0 && self.bubbles_used <= BUBBLES_FOR_STAR {
+            stars += 1;
+        }
+        stars
+    }
+
+    /// coverage in whole percent
+    pub fn coverage_percent(&self) -> u32 {
+        (self.coverage * 100.).floor() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::assists::Assists;
+    use crate::level::Level;
+
+    fn score(coverage: f32, bubbles_used: u32, deaths: u32) -> LevelScore {
+        LevelScore {
+            coverage,
+            bubbles_used,
+            deaths,
+            orbs_collected: 0,
+            orbs_total: 0,
+        }
+    }
+
+    #[test]
+    fn star_thresholds() {
+        assert_eq!(score(0., 0, 0).stars(), 2);
+        assert_eq!(score(0., 0, 1).stars(), 1);
+        assert_eq!(score(COVERAGE_FOR_STAR, 0, 1).stars(), 2);
+        assert_eq!(score(COVERAGE_FOR_STAR - 0.001, 0, 1).stars(), 1);
+        assert_eq!(score(1., BUBBLES_FOR_STAR, 0).stars(), MAX_STARS);
+        assert_eq!(score(1., BUBBLES_FOR_STAR + 1, 0).stars(), 2);
+    }
+
+    #[test]
+    fn coverage_percent_rounds_down() {
+        assert_eq!(score(0.999, 0, 0).coverage_percent(), 99);
+        assert_eq!(score(1., 0, 0).coverage_percent(), 100);
+        assert_eq!(score(0.255, 0, 0).coverage_percent(), 25);
+    }
+
+    #[test]
+    fn from_game() {
+        let mut game = Game::new(Level::from_ascii(&["S.", "##"]), Assists::default());
+        game.deaths = 2;
+        game.bubbles_used = 5;
+        let score = LevelScore::new(&game);
+        assert_eq!(score, self::score(0., 5, 2));
+        assert_eq!(score.stars(), 1);
+    }
+}
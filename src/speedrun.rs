@@ -0,0 +1,231 @@
+use crate::entity::game::TIMESTEP;
+use serde::{Deserialize, Serialize};
+
+/// Fastest times in ticks, per level and for a full run starting at the first level
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersonalBests {
+    pub levels: Vec<Option<u32>>,
+    pub full_run: Option<u32>,
+}
+
+impl PersonalBests {
+    pub fn level(&self, level_idx: usize) -> Option<u32> {
+        self.levels.get(level_idx).copied().flatten()
+    }
+}
+
+/// The time of a finished level or run compared against the personal best before it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Split {
+    pub ticks: u32,
+    pub best: Option<u32>,
+}
+
+impl Split {
+    /// negative when faster than the previous best
+    pub fn delta(&self) -> Option<i64> {
+        self.best.map(|best| self.ticks as i64 - best as i64)
+    }
+
+    pub fn is_new_best(&self) -> bool {
+        self.best.is_none_or(|best| self.ticks < best)
+    }
+}
+
+/// Counts simulation ticks, restarts do not reset the clock, only starting a run or the next level does
+#[derive(Debug, Clone, Default)]
+pub struct SpeedrunTimer {
+    pub level_ticks: u32,
+    pub run_ticks: u32,
+    /// a run only counts towards [`PersonalBests::full_run`] if it started at the first level
+    pub full_run: bool,
+    /// whether any tick of the run was simulated with assists, which keeps it out of the bests
+    pub assisted: bool,
+    pub splits: Vec<Split>,
+    /// the total of a finished full run
+    pub total: Option<Split>,
+}
+
+impl SpeedrunTimer {
+    pub fn start_run(&mut self, level_idx: usize) {
+        self.level_ticks = 0;
+        self.run_ticks = 0;
+        self.full_run = level_idx == 0;
+        self.assisted = false;
+        self.splits.clear();
+        self.total = None;
+    }
+
+    pub fn start_level(&mut self) {
+        self.level_ticks = 0;
+    }
+
+    /// `assisted` if the tick was simulated with any of the simulated assists
+    pub fn tick(&mut self, assisted: bool) {
+        self.assisted |= assisted;
+        self.level_ticks += 1;
+        self.run_ticks += 1;
+    }
+
+    /// Records the split of a finished level, updating the personal bests
+    pub fn split(&mut self, level_idx: usize, bests: &mut PersonalBests) -> Split {
+        let split = Split {
+            ticks: self.level_ticks,
+            best: bests.level(level_idx),
+        };
+        if split.is_new_best() {
+            if bests.levels.len() <= level_idx {
+                bests.levels.resize(level_idx + 1, None);
+            }
+            bests.levels[level_idx] = Some(split.ticks);
+        }
+        self.splits.push(split);
+        split
+    }
+
+    /// Finishes the run, recording the [`Self::total`] if this was a full run that was never
+    /// [`Self::assisted`]
+    pub fn finish_run(&mut self, bests: &mut PersonalBests) {
+        if !self.full_run || self.assisted {
+            return;
+        }
+        let split = Split {
+            ticks: self.run_ticks,
+            best: bests.full_run,
+        };
+        if split.is_new_best() {
+            bests.full_run = Some(split.ticks);
+        }
+        self.total = Some(split);
+    }
+
+    pub fn last_split(&self) -> Option<&Split> {
+        self.splits.last()
+    }
+}
+
+/// formats ticks as `m:ss.cc`
+pub fn format_ticks(ticks: u32) -> String {
+    let centis = (TIMESTEP * ticks).as_millis() / 10;
+    format!(
+        "{}:{:02}.{:02}",
+        centis / 6000,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// formats a tick delta as `+m:ss.cc` or `-m:ss.cc`
+pub fn format_delta(delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!("{sign}{}", format_ticks(delta.unsigned_abs() as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticks(timer: &mut SpeedrunTimer, ticks: u32) {
+        for _ in 0..ticks {
+            timer.tick(false);
+        }
+    }
+
+    #[test]
+    fn splits_update_bests() {
+        let mut bests = PersonalBests::default();
+        let mut timer = SpeedrunTimer::default();
+        timer.start_run(0);
+        ticks(&mut timer, 100);
+        let split = timer.split(0, &mut bests);
+        assert_eq!(
+            split,
+            Split {
+                ticks: 100,
+                best: None
+            }
+        );
+        assert!(split.is_new_best());
+        assert_eq!(split.delta(), None);
+
+        timer.start_level();
+        ticks(&mut timer, 50);
+        timer.split(2, &mut bests);
+        assert_eq!(bests.levels, vec![Some(100), None, Some(50)]);
+        assert_eq!(timer.run_ticks, 150);
+
+        timer.start_run(0);
+        ticks(&mut timer, 120);
+        let split = timer.split(0, &mut bests);
+        assert!(!split.is_new_best());
+        assert_eq!(split.delta(), Some(20));
+        assert_eq!(bests.level(0), Some(100));
+
+        timer.start_run(0);
+        ticks(&mut timer, 90);
+        assert_eq!(timer.split(0, &mut bests).delta(), Some(-10));
+        assert_eq!(bests.level(0), Some(90));
+    }
+
+    #[test]
+    fn only_full_runs_set_a_total() {
+        let mut bests = PersonalBests::default();
+        let mut timer = SpeedrunTimer::default();
+        timer.start_run(1);
+        ticks(&mut timer, 10);
+        timer.finish_run(&mut bests);
+        assert_eq!(timer.total, None);
+        assert_eq!(bests.full_run, None);
+
+        timer.start_run(0);
+        ticks(&mut timer, 30);
+        timer.finish_run(&mut bests);
+        assert_eq!(
+            timer.total,
+            Some(Split {
+                ticks: 30,
+                best: None
+            })
+        );
+        assert_eq!(bests.full_run, Some(30));
+
+        timer.start_run(0);
+        ticks(&mut timer, 40);
+        timer.finish_run(&mut bests);
+        assert_eq!(timer.total.unwrap().delta(), Some(10));
+        assert_eq!(bests.full_run, Some(30));
+    }
+
+    #[test]
+    fn assisted_runs_set_no_best() {
+        let mut bests = PersonalBests::default();
+        let mut timer = SpeedrunTimer::default();
+        timer.start_run(0);
+        ticks(&mut timer, 10);
+        timer.tick(true);
+        ticks(&mut timer, 10);
+        assert!(timer.assisted);
+        timer.finish_run(&mut bests);
+        assert_eq!(timer.total, None);
+        assert_eq!(bests.full_run, None);
+
+        timer.start_run(0);
+        assert!(!timer.assisted);
+        ticks(&mut timer, 30);
+        timer.finish_run(&mut bests);
+        assert_eq!(bests.full_run, Some(30));
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_ticks(0), "0:00.00");
+        assert_eq!(format_ticks(60), "0:01.00");
+        assert_eq!(format_ticks(90), "0:01.50");
+        assert_eq!(format_ticks(60 * 75 + 3), "1:15.05");
+        assert_eq!(format_ticks(60 * 60 * 10), "10:00.00");
+        assert_eq!(format_delta(0), "+0:00.00");
+        assert_eq!(format_delta(30), "+0:00.50");
+        assert_eq!(format_delta(-60 * 61), "-1:01.00");
+    }
+}
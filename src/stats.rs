@@ -0,0 +1,143 @@
+use crate::entity::game::{GameEvent, TIMESTEP};
+use crate::save::SaveStore;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const STATS_EXPORT_KEY: &str = "stats.json";
+/// where [`Stats::export`] ends up on this platform, for the stats screen
+#[cfg(not(target_arch = "wasm32"))]
+pub const STATS_EXPORT_LOCATION: &str = "SAVE/STATS.JSON";
+/// where [`Stats::export`] ends up on this platform, for the stats screen
+#[cfg(target_arch = "wasm32")]
+pub const STATS_EXPORT_LOCATION: &str = "BROWSER LOCAL STORAGE";
+
+/// Lifetime counters over all live play, replays don't count
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// deaths indexed by level
+    pub deaths: Vec<u32>,
+    pub bubbles_spawned: u64,
+    pub splashes_painted: u64,
+    pub jumps: u64,
+    pub ticks_played: u64,
+    /// ticks played with any assist enabled
+    pub assisted_ticks: u64,
+}
+
+impl Stats {
+    pub fn tick(&mut self, assisted: bool) {
+        self.ticks_played += 1;
+        if assisted {
+            self.assisted_ticks += 1;
+        }
+    }
+
+    /// Counts the events that happened on the level
+    pub fn record(&mut self, level_idx: usize, events: &[GameEvent]) {
+        for event in events {
+            match event {
+                GameEvent::Death { .. } => {
+                    if self.deaths.len() <= level_idx {
+                        self.deaths.resize(level_idx + 1, 0);
+                    }
+                    self.deaths[level_idx] += 1;
+                }
+                GameEvent::BubbleSpawned { .. } => self.bubbles_spawned += 1,
+                GameEvent::Jump => self.jumps += 1,
+                GameEvent::Painted { splashes, .. } => self.splashes_painted += *splashes as u64,
+                _ => {}
+            }
+        }
+    }
+
+    pub fn level_deaths(&self, level_idx: usize) -> u32 {
+        self.deaths.get(level_idx).copied().unwrap_or(0)
+    }
+
+    pub fn total_deaths(&self) -> u32 {
+        self.deaths.iter().sum()
+    }
+
+    pub fn time_played(&self) -> Duration {
+        TIMESTEP.mul_f64(self.ticks_played as f64)
+    }
+
+    pub fn time_assisted(&self) -> Duration {
+        TIMESTEP.mul_f64(self.assisted_ticks as f64)
+    }
+
+    /// Writes the stats as pretty json to [`STATS_EXPORT_KEY`], for balancing
+    pub fn export(&self, store: &mut dyn SaveStore) -> anyhow::Result<()> {
+        store.store(STATS_EXPORT_KEY, &serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// formats a duration as `h:mm:ss`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::MemorySaveStore;
+    use glam::Vec2;
+
+    #[test]
+    fn record_counts_events() {
+        let mut stats = Stats::default();
+        stats.record(
+            2,
+            &[
+                GameEvent::Death { pos: Vec2::ZERO },
+                GameEvent::BubbleSpawned { pos: Vec2::ZERO },
+                GameEvent::Jump,
+                GameEvent::Painted {
+                    splashes: 3,
+                    coverage: 0.1,
+                },
+                GameEvent::Painted {
+                    splashes: 0,
+                    coverage: 0.2,
+                },
+                GameEvent::LevelComplete,
+            ],
+        );
+        stats.record(0, &[GameEvent::Death { pos: Vec2::ZERO }]);
+        assert_eq!(stats.deaths, vec![1, 0, 1]);
+        assert_eq!(stats.total_deaths(), 2);
+        assert_eq!(stats.level_deaths(5), 0);
+        assert_eq!(
+            (stats.bubbles_spawned, stats.jumps, stats.splashes_painted),
+            (1, 1, 3)
+        );
+    }
+
+    #[test]
+    fn tick_keeps_time() {
+        let mut stats = Stats::default();
+        for tick in 0..90 {
+            stats.tick(tick % 3 == 0);
+        }
+        assert_eq!((stats.ticks_played, stats.assisted_ticks), (90, 30));
+        assert_eq!(stats.time_played().as_millis(), 1500);
+        assert_eq!(stats.time_assisted().as_millis(), 500);
+
+        // beyond what fits into u32 ticks
+        stats.ticks_played = 1 << 33;
+        assert_eq!(stats.time_played().as_secs() / 3600, 39768);
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let mut stats = Stats::default();
+        stats.record(1, &[GameEvent::Death { pos: Vec2::ZERO }, GameEvent::Jump]);
+        stats.tick(true);
+        let mut store = MemorySaveStore::default();
+        stats.export(&mut store).unwrap();
+        let exported = store.load(STATS_EXPORT_KEY).unwrap().unwrap();
+        assert_eq!(serde_json::from_slice::<Stats>(&exported).unwrap(), stats);
+    }
+}
@@ -0,0 +1,397 @@
+use crate::achievements::Achievement;
+use crate::flow::{AssistOption, GameFlow, LEVEL_SELECT_COLUMNS, Screen};
+use crate::rendering::framedata::VIEWPORT_SIZE;
+use crate::score::{LevelScore, MAX_STARS};
+use crate::speedrun::{Split, format_delta, format_ticks};
+use crate::stats::{STATS_EXPORT_LOCATION, format_duration};
+use glam::{Vec2, Vec4, vec2, vec4};
+
+pub const GLYPH_WIDTH: u32 = 3;
+pub const GLYPH_HEIGHT: u32 = 5;
+/// horizontal advance of a glyph, in font pixels
+pub const GLYPH_ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+const TITLE_SCALE: f32 = 12.;
+const TEXT_SCALE: f32 = 4.;
+const HUD_SCALE: f32 = 3.;
+const DIM: f32 = 0.6;
+const FASTER: Vec4 = vec4(0.3, 1., 0.3, 1.);
+const SLOWER: Vec4 = vec4(1., 0.3, 0.3, 1.);
+const STAR: Vec4 = vec4(1., 0.85, 0.2, 1.);
+const NO_STAR: Vec4 = vec4(0.3, 0.3, 0.3, 1.);
+const TOAST_DIM: f32 = 0.8;
+const LOCKED_TINT: Vec4 = vec4(0.25, 0.25, 0.25, 1.);
+/// size of a level select cell and the thumbnail within it
+const CELL_SIZE: Vec2 = vec2(150., 145.);
+pub const THUMBNAIL_SIZE: Vec2 = vec2(130., 80.);
+
+/// A line of text in screen space, `pos` being its bottom left corner
+#[derive(Debug, Clone)]
+pub struct TextLine {
+    pub text: String,
+    pub pos: Vec2,
+    pub scale: f32,
+    pub color: Vec4,
+}
+
+/// A solid rectangle in screen space, `pos` being its bottom left corner
+#[derive(Debug, Copy, Clone)]
+pub struct ColorRect {
+    pub pos: Vec2,
+    pub size: Vec2,
+    pub color: Vec4,
+}
+
+/// The thumbnail of a level in screen space, see [`crate::level::Level::thumbnail`]
+#[derive(Debug, Copy, Clone)]
+pub struct Thumbnail {
+    pub level_idx: usize,
+    pub pos: Vec2,
+    pub size: Vec2,
+    pub tint: Vec4,
+}
+
+/// Everything drawn on top of the game, in screen space spanning [`VIEWPORT_SIZE`]
+///
+/// Drawn back to front: the dim, rects, thumbnails and finally the text.
+#[derive(Debug, Clone, Default)]
+pub struct Overlay {
+    /// alpha of a black quad covering the game
+    pub dim: f32,
+    pub rects: Vec<ColorRect>,
+    pub thumbnails: Vec<Thumbnail>,
+    pub lines: Vec<TextLine>,
+}
+
+impl Overlay {
+    pub fn text(&mut self, text: impl Into<String>, pos: Vec2, scale: f32, color: Vec4) {
+        self.lines.push(TextLine {
+            text: text.into(),
+            pos,
+            scale,
+            color,
+        });
+    }
+
+    pub fn centered(&mut self, text: impl Into<String>, y: f32, scale: f32, color: Vec4) {
+        let text = text.into();
+        let x = (VIEWPORT_SIZE.x - text_width(&text, scale)) * 0.5;
+        self.text(text, vec2(x, y), scale, color);
+    }
+}
+
+pub fn text_width(text: &str, scale: f32) -> f32 {
+    let chars = text.chars().count() as u32;
+    (chars * GLYPH_ADVANCE).saturating_sub(1) as f32 * scale
+}
+
+pub fn screen_overlay(flow: &GameFlow) -> Overlay {
+    let white = Vec4::ONE;
+    let color = flow.game.player.color();
+    let mut overlay = Overlay::default();
+    match flow.screen {
+        Screen::Title => {
+            overlay.dim = DIM;
+            overlay.centered("COLORBUBBLE", 380., TITLE_SCALE, color);
+            overlay.centered("PRESS ENTER TO START", 240., TEXT_SCALE, white);
+            overlay.centered("L FOR LEVEL SELECT", 200., TEXT_SCALE, white);
+            overlay.centered("TAB FOR STATS", 160., TEXT_SCALE, white);
+            overlay.centered("O FOR ASSISTS", 120., TEXT_SCALE, white);
+            overlay.centered("ESC TO QUIT", 80., TEXT_SCALE, white);
+        }
+        Screen::Playing if flow.save.settings.show_timer => {
+            let timer = &flow.timer;
+            overlay.text(
+                format!("LEVEL {}", format_ticks(timer.level_ticks)),
+                vec2(10., VIEWPORT_SIZE.y - 25.),
+                HUD_SCALE,
+                white,
+            );
+            overlay.text(
+                format!("RUN   {}", format_ticks(timer.run_ticks)),
+                vec2(10., VIEWPORT_SIZE.y - 45.),
+                HUD_SCALE,
+                white,
+            );
+        }
+        Screen::Playing => {}
+        Screen::Paused => {
+            overlay.dim = DIM;
+            overlay.centered("PAUSED", 380., TITLE_SCALE, color);
+            overlay.centered("ESC TO RESUME", 240., TEXT_SCALE, white);
+            overlay.centered("ENTER FOR TITLE SCREEN", 200., TEXT_SCALE, white);
+            overlay.centered("O FOR ASSISTS", 160., TEXT_SCALE, white);
+        }
+        Screen::LevelComplete => {
+            overlay.dim = DIM;
+            overlay.centered(
+                format!("LEVEL {} COMPLETE", flow.level_idx + 1),
+                420.,
+                TEXT_SCALE * 2.,
+                color,
+            );
+            if let Some(score) = &flow.score {
+                score_lines(&mut overlay, score, 340.);
+            }
+            if let Some(split) = flow.timer.last_split() {
+                split_lines(&mut overlay, "TIME", split, 220.);
+            }
+            overlay.centered("PRESS ENTER TO CONTINUE", 130., TEXT_SCALE, white);
+        }
+        Screen::Finished => {
+            overlay.dim = DIM;
+            overlay.centered("THE END", 420., TITLE_SCALE, color);
+            if let Some(total) = &flow.timer.total {
+                split_lines(&mut overlay, "TOTAL", total, 340.);
+            }
+            overlay.centered("THANKS FOR PLAYING!", 300., TEXT_SCALE, white);
+            overlay.centered("MADE BY FIRESTAR99 AND FRIZ64", 260., TEXT_SCALE, white);
+            overlay.centered("PRESS ENTER", 160., TEXT_SCALE, white);
+        }
+        Screen::Stats => {
+            overlay.dim = DIM;
+            overlay.centered("STATS", 480., TEXT_SCALE * 2., color);
+            let stats = &flow.save.stats;
+            let lines = [
+                format!("TIME PLAYED {}", format_duration(stats.time_played())),
+                format!("WITH ASSISTS {}", format_duration(stats.time_assisted())),
+                format!("DEATHS {}", stats.total_deaths()),
+                format!("JUMPS {}", stats.jumps),
+                format!("BUBBLES {}", stats.bubbles_spawned),
+                format!("SPLASHES PAINTED {}", stats.splashes_painted),
+            ];
+            for (i, line) in lines.into_iter().enumerate() {
+                overlay.centered(line, 410. - i as f32 * 30., TEXT_SCALE, white);
+            }
+            overlay.centered("DEATHS PER LEVEL", 250., HUD_SCALE, white);
+            let per_level = (0..flow.levels.len())
+                .map(|idx| format!("{}:{}", idx + 1, stats.level_deaths(idx)))
+                .collect::<Vec<_>>();
+            for (i, row) in per_level.chunks(7).enumerate() {
+                overlay.centered(row.join(" "), 225. - i as f32 * 22., HUD_SCALE, white);
+            }
+            let export = if flow.stats_exported {
+                format!("EXPORTED TO {STATS_EXPORT_LOCATION}")
+            } else {
+                "ENTER TO EXPORT AS JSON".to_string()
+            };
+            overlay.centered(export, 120., TEXT_SCALE, white);
+            overlay.centered("ESC TO RETURN", 80., TEXT_SCALE, white);
+        }
+        Screen::LevelSelect => {
+            overlay.dim = DIM;
+            overlay.centered("SELECT LEVEL", 540., TEXT_SCALE * 2., color);
+            level_select(&mut overlay, flow, color);
+            overlay.centered("ENTER TO PLAY  ESC TO RETURN", 30., TEXT_SCALE, white);
+        }
+        Screen::Assists => {
+            overlay.dim = DIM;
+            overlay.centered("ASSISTS", 460., TEXT_SCALE * 2., color);
+            let assists = &flow.save.settings.assists;
+            for (i, option) in AssistOption::ALL.into_iter().enumerate() {
+                let text = match option {
+                    AssistOption::GameSpeed => {
+                        format!("GAME SPEED {}%", (assists.game_speed * 100.).round())
+                    }
+                    AssistOption::Outlines => {
+                        format!("PLATFORM OUTLINES {}", on_off(assists.outlines))
+                    }
+                    AssistOption::RespawnAtLastStanding => format!(
+                        "RESPAWN WHERE YOU STOOD {}",
+                        on_off(assists.respawn_at_last_standing)
+                    ),
+                    AssistOption::Invincible => {
+                        format!("INVINCIBLE {}", on_off(assists.invincible))
+                    }
+                };
+                let line_color = if i == flow.selected_assist {
+                    color
+                } else {
+                    white
+                };
+                overlay.centered(text, 360. - i as f32 * 40., TEXT_SCALE, line_color);
+            }
+            overlay.centered(
+                "RESPAWN AND INVINCIBLE CHANGES RESTART THE ATTEMPT",
+                150.,
+                HUD_SCALE,
+                Vec4::splat(TOAST_DIM),
+            );
+            overlay.centered("ENTER TO CHANGE  ESC TO RETURN", 80., TEXT_SCALE, white);
+        }
+    }
+    if let Some(achievement) = flow.toasts.current() {
+        toast(&mut overlay, achievement);
+    }
+    overlay
+}
+
+/// a grid of thumbnails with the level number and best time below each
+fn level_select(overlay: &mut Overlay, flow: &GameFlow, selection_color: Vec4) {
+    let columns = LEVEL_SELECT_COLUMNS as f32;
+    let origin = vec2((VIEWPORT_SIZE.x - CELL_SIZE.x * columns) * 0.5, 500.);
+    for (idx, level) in flow.levels.iter().enumerate() {
+        let cell = vec2(
+            (idx % LEVEL_SELECT_COLUMNS) as f32,
+            (idx / LEVEL_SELECT_COLUMNS) as f32,
+        );
+        let top_left = origin + vec2(cell.x * CELL_SIZE.x, -cell.y * CELL_SIZE.y);
+        let center = top_left + vec2(CELL_SIZE.x * 0.5, -THUMBNAIL_SIZE.y * 0.5);
+
+        let level_size = level.size.as_vec2();
+        let size = level_size * (THUMBNAIL_SIZE / level_size).min_element();
+        let pos = center - size * 0.5;
+        if idx == flow.selected_level {
+            overlay.rects.push(ColorRect {
+                pos: pos - 4.,
+                size: size + 8.,
+                color: selection_color,
+            });
+        }
+
+        let unlocked = flow.save.is_unlocked(idx);
+        overlay.thumbnails.push(Thumbnail {
+            level_idx: idx,
+            pos,
+            size,
+            tint: if unlocked { Vec4::ONE } else { LOCKED_TINT },
+        });
+
+        let label = if !unlocked {
+            format!("{} LOCKED", idx + 1)
+        } else if let Some(best) = flow.save.bests.level(idx) {
+            format!("{} {}", idx + 1, format_ticks(best))
+        } else {
+            format!("{} -:--.--", idx + 1)
+        };
+        let x = center.x - text_width(&label, HUD_SCALE) * 0.5;
+        let y = top_left.y - THUMBNAIL_SIZE.y - 25.;
+        overlay.text(label, vec2(x, y), HUD_SCALE, Vec4::ONE);
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled { "ON" } else { "OFF" }
+}
+
+/// an unlocked achievement at the top of the screen
+fn toast(overlay: &mut Overlay, achievement: &Achievement) {
+    let top = VIEWPORT_SIZE.y - 30.;
+    overlay.centered("ACHIEVEMENT UNLOCKED", top, HUD_SCALE, STAR);
+    overlay.centered(achievement.name, top - 30., TEXT_SCALE, Vec4::ONE);
+    overlay.centered(
+        achievement.description,
+        top - 55.,
+        HUD_SCALE,
+        Vec4::splat(TOAST_DIM),
+    );
+}
+
+/// a time and below it its delta to the previous best
+fn split_lines(overlay: &mut Overlay, label: &str, split: &Split, y: f32) {
+    overlay.centered(
+        format!("{label} {}", format_ticks(split.ticks)),
+        y,
+        TEXT_SCALE,
+        Vec4::ONE,
+    );
+    let (text, color) = match split.delta() {
+        Some(delta) if split.is_new_best() => {
+            (format!("{} NEW BEST!", format_delta(delta)), FASTER)
+        }
+        Some(delta) => (format_delta(delta), SLOWER),
+        None => ("NEW BEST!".to_string(), FASTER),
+    };
+    overlay.centered(text, y - 30., TEXT_SCALE, color);
+}
+
+/// the stars, and below them what they were earned with
+fn score_lines(overlay: &mut Overlay, score: &LevelScore, y: f32) {
+    let scale = TEXT_SCALE * 2.;
+    let stars = score.stars();
+    let x = (VIEWPORT_SIZE.x - text_width("* ".repeat(MAX_STARS as usize).trim_end(), scale)) * 0.5;
+    for i in 0..MAX_STARS {
+        let color = if i < stars { STAR } else { NO_STAR };
+        let offset = (i * GLYPH_ADVANCE * 2) as f32 * scale;
+        overlay.text("*", vec2(x + offset, y), scale, color);
+    }
+    overlay.centered(
+        format!(
+            "PAINTED {}%  BUBBLES {}  DEATHS {}",
+            score.coverage_percent(),
+            score.bubbles_used,
+            score.deaths
+        ),
+        y - 40.,
+        TEXT_SCALE,
+        Vec4::ONE,
+    );
+    if score.orbs_total > 0 {
+        overlay.centered(
+            format!("ORBS {}/{}", score.orbs_collected, score.orbs_total),
+            y - 70.,
+            TEXT_SCALE,
+            Vec4::ONE,
+        );
+    }
+}
+
+/// 3x5 pixel font, each row is 3 bits with the MSB being the leftmost pixel, rows from top to bottom
+pub fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '*' => [0b101, 0b010, 0b101, 0b000, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}